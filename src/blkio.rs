@@ -24,6 +24,7 @@ pub struct BlkIoController {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Per-device information
 pub struct BlkIoData {
     /// The major number of the device.
@@ -35,6 +36,7 @@ pub struct BlkIoData {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Per-device activity from the control group.
 pub struct IoService {
     /// The major number of the device.
@@ -142,6 +144,7 @@ fn parse_blkio_data(s: String) -> Result<Vec<BlkIoData>> {
 /// Current state and statistics about how throttled are the block devices when accessed from the
 /// controller's control group.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlkIoThrottle {
     /// Statistics about the bytes transferred between the block devices by the tasks in this
     /// control group.
@@ -177,6 +180,7 @@ pub struct BlkIoThrottle {
 
 /// Statistics and state of the block devices.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlkIo {
     /// The number of BIOS requests merged into I/O requests by the control group's tasks.
     pub io_merged: Vec<IoService>,
@@ -274,28 +278,36 @@ impl ControllerInternal for BlkIoController {
         let res: &BlkIoResources = &res.blkio;
 
         if res.update_values {
-            let _ = self.set_weight(res.weight as u64);
-            let _ = self.set_leaf_weight(res.leaf_weight as u64);
+            if let Some(weight) = res.weight {
+                self.set_weight(weight as u64)?;
+            }
+            if let Some(leaf_weight) = res.leaf_weight {
+                self.set_leaf_weight(leaf_weight as u64)?;
+            }
 
             for dev in &res.weight_device {
-                let _ = self.set_weight_for_device(dev.major, dev.minor, dev.weight as u64);
-                let _ = self.set_leaf_weight_for_device(dev.major, dev.minor, dev.leaf_weight as u64);
+                if let Some(weight) = dev.weight {
+                    self.set_weight_for_device(dev.major, dev.minor, weight as u64)?;
+                }
+                if let Some(leaf_weight) = dev.leaf_weight {
+                    self.set_leaf_weight_for_device(dev.major, dev.minor, leaf_weight as u64)?;
+                }
             }
 
             for dev in &res.throttle_read_bps_device {
-                let _ = self.throttle_read_bps_for_device(dev.major, dev.minor, dev.rate);
+                self.throttle_read_bps_for_device(dev.major, dev.minor, dev.rate)?;
             }
 
             for dev in &res.throttle_write_bps_device {
-                let _ = self.throttle_write_bps_for_device(dev.major, dev.minor, dev.rate);
+                self.throttle_write_bps_for_device(dev.major, dev.minor, dev.rate)?;
             }
 
             for dev in &res.throttle_read_iops_device {
-                let _ = self.throttle_read_iops_for_device(dev.major, dev.minor, dev.rate);
+                self.throttle_read_iops_for_device(dev.major, dev.minor, dev.rate)?;
             }
 
             for dev in &res.throttle_write_iops_device {
-                let _ = self.throttle_write_iops_for_device(dev.major, dev.minor, dev.rate);
+                self.throttle_write_iops_for_device(dev.major, dev.minor, dev.rate)?;
             }
         }
 
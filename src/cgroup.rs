@@ -1,11 +1,15 @@
 //! This module handles cgroup operations. Start here!
 
+use log::*;
+
 use crate::error::*;
 
 use crate::{CgroupPid, ControllIdentifier, Controller, Hierarchy, Resources, Subsystem};
 
 use std::convert::From;
-use std::path::Path;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 /// A control group is the central structure to this crate.
 ///
@@ -25,14 +29,17 @@ pub struct Cgroup<'b> {
 
     /// The hierarchy.
     hier: &'b Hierarchy,
+
+    /// The path of the control group, relative to the root of the hierarchy.
+    path: PathBuf,
 }
 
 impl<'b> Cgroup<'b> {
     /// Create this control group.
-    fn create(&self) {
-        for subsystem in &self.subsystems {
-            subsystem.to_controller().create();
-        }
+    pub(crate) fn create(&self) -> Result<()> {
+        self.subsystems
+            .iter()
+            .try_for_each(|sub| sub.to_controller().create())
     }
 
     /// Create a new control group in the hierarchy `hier`, with name `path`.
@@ -43,7 +50,9 @@ impl<'b> Cgroup<'b> {
     /// destroyed.
     pub fn new<P: AsRef<Path>>(hier: &Hierarchy, path: P) -> Cgroup {
         let cg = Cgroup::load(hier, path);
-        cg.create();
+        if let Err(e) = cg.create() {
+            warn!("error creating control group: {:?}", e);
+        }
         cg
     }
 
@@ -67,6 +76,7 @@ impl<'b> Cgroup<'b> {
         let cg = Cgroup {
             subsystems: subsystems,
             hier: hier,
+            path: path.to_path_buf(),
         };
 
         cg
@@ -102,10 +112,34 @@ impl<'b> Cgroup<'b> {
     }
 
     /// Apply a set of resource limits to the control group.
+    ///
+    /// The raw `unified` resources are written last, see `Resources::unified`.
     pub fn apply(&self, res: &Resources) -> Result<()> {
         self.subsystems
             .iter()
-            .try_fold((), |_, e| e.to_controller().apply(res))
+            .try_fold((), |_, e| e.to_controller().apply(res))?;
+        self.apply_unified(res)
+    }
+
+    /// Writes the raw `unified` resources to the interface files of the control group.
+    fn apply_unified(&self, res: &Resources) -> Result<()> {
+        res.validate_unified(self.hier.v2())?;
+        let dir = self.hier.root().join(&self.path);
+        let mut keys = res.unified.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let path = dir.join(key);
+            // control group files can never be created, so a missing one is not supported
+            if !path.exists() && dir.exists() {
+                return Err(Error::new(ErrorKind::Unsupported));
+            }
+            OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(res.unified[key].as_bytes()))
+                .map_err(|e| Error::with_cause(ErrorKind::WriteFailed, e))?;
+        }
+        Ok(())
     }
 
     /// Retrieve a container based on type inference.
@@ -133,6 +167,20 @@ impl<'b> Cgroup<'b> {
         None
     }
 
+    /// Resets the maximum observed resource usages of the control group, such as the peak memory
+    /// usage, so that the peaks of a new phase of the tasks' work can be measured.
+    ///
+    /// Covers the memory (including kernel, TCP and memory+swap usage where available) and the
+    /// hugetlb controllers. The memory peaks of the unified hierarchy cannot be reset, see
+    /// `MemController::reset_peaks()`.
+    pub fn reset_peaks(&self) -> Result<()> {
+        self.subsystems.iter().try_for_each(|sub| match sub {
+            Subsystem::Mem(c) => c.reset_peaks(),
+            Subsystem::HugeTlb(c) => c.reset_peaks(),
+            _ => Ok(()),
+        })
+    }
+
     /// Removes a task from the control group.
     ///
     /// Note that this means that the task will be moved back to the root control group in the
@@ -12,6 +12,7 @@
 //! ```rust,no_run
 //! # use cgroups::*;
 //! # use cgroups::devices::*;
+//! # use cgroups::freezer::*;
 //! # use cgroups::cgroup_builder::*;
 //! let v1 = cgroups::hierarchies::V1::new();
 //! let cgroup: Cgroup = CgroupBuilder::new("hello", &v1)
@@ -51,18 +52,25 @@
 //!              .read(6, 1, 10)
 //!              .write(11, 1, 100)
 //!          .done()
-//!      .build();
+//!      .rdma()
+//!          .limit("mlx4_0".to_string(), Some(2), Some(2000))
+//!          .done()
+//!      .freezer()
+//!          .state(FreezerState::Thawed)
+//!          .done()
+//!      .build()
+//!      .expect("failed to create the control group");
 //! ```
 use crate::error::*;
 
-use crate::{pid, BlkIoDeviceResource, BlkIoDeviceThrottleResource,  Cgroup, DeviceResource, Hierarchy, HugePageResource, NetworkPriority, Resources};
+use crate::{cpu, cpuset, freezer, memory, pid, BlkIoDeviceResource, BlkIoDeviceThrottleResource,  Cgroup, DeviceResource, Hierarchy, HugePageResource, NetworkPriority, RdmaResource, Resources};
 
 macro_rules! gen_setter {
     ($res:ident, $cont:ident, $func:ident, $name:ident, $ty:ty) => {
         /// See the similarly named function in the respective controller.
         pub fn $name(mut self, $name: $ty) -> Self {
             self.cgroup.resources.$res.update_values = true;
-            self.cgroup.resources.$res.$name = $name;
+            self.cgroup.resources.$res.$name = $name.into();
             self
         }
     }
@@ -74,6 +82,7 @@ pub struct CgroupBuilder<'a> {
     hierarchy: &'a Hierarchy,
     /// Internal, unsupported field: use the associated builders instead.
     resources: Resources,
+    delete_on_failure: bool,
 }
 
 impl<'a> CgroupBuilder<'a> {
@@ -85,9 +94,17 @@ impl<'a> CgroupBuilder<'a> {
             name: name.to_owned(),
             hierarchy: hierarchy,
             resources: Resources::default(),
+            delete_on_failure: false,
         }
     }
 
+    /// If `delete` is true and `build()` fails, the control group is deleted again, unless it
+    /// already existed before `build()` was called.
+    pub fn delete_on_failure(mut self, delete: bool) -> Self {
+        self.delete_on_failure = delete;
+        self
+    }
+
     /// Builds the memory resources of the control group.
     pub fn memory(self) -> MemoryResourceBuilder<'a> {
         MemoryResourceBuilder {
@@ -140,11 +157,39 @@ impl<'a> CgroupBuilder<'a> {
         }
     }
 
+    /// Builds the RDMA/IB resources available to the control group.
+    pub fn rdma(self) -> RdmaResourceBuilder<'a> {
+        RdmaResourceBuilder {
+            cgroup: self,
+        }
+    }
+
+    /// Builds the freezer state of the control group.
+    pub fn freezer(self) -> FreezerResourceBuilder<'a> {
+        FreezerResourceBuilder {
+            cgroup: self,
+        }
+    }
+
     /// Finalize the control group, consuming the builder and creating the control group.
-    pub fn build(self) -> Cgroup<'a> {
-        let cg = Cgroup::new(self.hierarchy, self.name);
-        cg.apply(&self.resources);
-        cg
+    ///
+    /// The resources are checked with `Resources::validate()` first, in which case nothing is
+    /// created. Fails if the control group could not be created or if any of the resources could
+    /// not be applied; see `delete_on_failure()` for what happens to the control group then.
+    pub fn build(self) -> Result<Cgroup<'a>> {
+        self.resources.validate(self.hierarchy)?;
+
+        let cg = Cgroup::load(self.hierarchy, &self.name);
+        let existed = cg.subsystems().iter().any(|sub| sub.to_controller().exists());
+        match cg.create().and_then(|_| cg.apply(&self.resources)) {
+            Ok(()) => Ok(cg),
+            Err(e) => {
+                if self.delete_on_failure && !existed {
+                    cg.delete();
+                }
+                Err(e)
+            }
+        }
     }
 }
 
@@ -155,12 +200,21 @@ pub struct MemoryResourceBuilder<'a> {
 
 impl<'a> MemoryResourceBuilder<'a> {
 
-    gen_setter!(memory, MemController, set_kmem_limit, kernel_memory_limit, u64);
-    gen_setter!(memory, MemController, set_limit, memory_hard_limit, u64);
-    gen_setter!(memory, MemController, set_soft_limit, memory_soft_limit, u64);
-    gen_setter!(memory, MemController, set_tcp_limit, kernel_tcp_memory_limit, u64);
-    gen_setter!(memory, MemController, set_memswap_limit, memory_swap_limit, u64);
+    gen_setter!(memory, MemController, set_kmem_limit, kernel_memory_limit, i64);
+    gen_setter!(memory, MemController, set_limit, memory_hard_limit, i64);
+    gen_setter!(memory, MemController, set_soft_limit, memory_soft_limit, i64);
+    gen_setter!(memory, MemController, set_tcp_limit, kernel_tcp_memory_limit, i64);
+    gen_setter!(memory, MemController, set_memswap_limit, memory_swap_limit, i64);
     gen_setter!(memory, MemController, set_swappiness, swappiness, u64);
+    gen_setter!(memory, MemController, set_min, memory_min, i64);
+    gen_setter!(memory, MemController, set_low, memory_low, i64);
+    gen_setter!(memory, MemController, set_high, memory_high, i64);
+    gen_setter!(memory, MemController, set_swap_high, swap_high, i64);
+    gen_setter!(memory, MemController, set_zswap_max, zswap_max, i64);
+    gen_setter!(memory, MemController, set_oom_kill_disable, oom_kill_disable, bool);
+    gen_setter!(memory, MemController, set_oom_group, oom_group, bool);
+    gen_setter!(memory, MemController, set_use_hierarchy, use_hierarchy, bool);
+    gen_setter!(memory, MemController, set_move_charge_at_immigrate, move_charge_at_immigrate, memory::MoveCharge);
 
     /// Finish the construction of the memory resources of a control group.
     pub fn done(self) -> CgroupBuilder<'a> {
@@ -190,13 +244,50 @@ pub struct CpuResourceBuilder<'a> {
 
 impl<'a> CpuResourceBuilder<'a> {
 
-    gen_setter!(cpu, CpuSetController, set_cpus, cpus, String);
-    gen_setter!(cpu, CpuSetController, set_mems, mems, String);
+    gen_setter!(cpu, CpuSetController, set_cpus, cpus, cpuset::CpuList);
+    gen_setter!(cpu, CpuSetController, set_mems, mems, cpuset::CpuList);
+    gen_setter!(cpu, CpuSetController, set_cpu_exclusive, cpu_exclusive, bool);
+    gen_setter!(cpu, CpuSetController, set_mem_exclusive, mem_exclusive, bool);
+    gen_setter!(cpu, CpuSetController, set_hardwall, mem_hardwall, bool);
+    gen_setter!(cpu, CpuSetController, set_memory_migration, memory_migrate, bool);
+    gen_setter!(cpu, CpuSetController, set_memory_spread_page, memory_spread_page, bool);
+    gen_setter!(cpu, CpuSetController, set_memory_spread_slab, memory_spread_slab, bool);
+    gen_setter!(cpu, CpuSetController, set_load_balancing, sched_load_balance, bool);
+    gen_setter!(cpu, CpuSetController, set_rebalance_relax_domain_level, sched_relax_domain_level, i64);
     gen_setter!(cpu, CpuController, set_shares, shares, u64);
-    gen_setter!(cpu, CpuController, set_cfs_quota, quota, i64);
+    gen_setter!(cpu, CpuController, set_cfs_quota, quota, cpu::CpuQuota);
     gen_setter!(cpu, CpuController, set_cfs_period, period, u64);
     gen_setter!(cpu, CpuController, set_rt_runtime, realtime_runtime, i64);
     gen_setter!(cpu, CpuController, set_rt_period, realtime_period, u64);
+    gen_setter!(cpu, CpuController, set_cfs_burst, burst, u64);
+
+    /// Set the minimum utilization the scheduler assumes for the tasks, in hundredths of a
+    /// percent, i.e. from `0` to `10000`: `5000` is 50%. Note that
+    /// `CpuController::set_uclamp_min()` takes a percentage instead.
+    pub fn uclamp_min(mut self, hundredths: u64) -> CpuResourceBuilder<'a> {
+        self.cgroup.resources.cpu.update_values = true;
+        self.cgroup.resources.cpu.uclamp_min = Some(hundredths);
+        self
+    }
+
+    /// Set the maximum utilization the scheduler assumes for the tasks, in hundredths of a
+    /// percent, i.e. from `0` to `10000`: `5000` is 50%. Note that
+    /// `CpuController::set_uclamp_max()` takes a percentage instead.
+    pub fn uclamp_max(mut self, hundredths: u64) -> CpuResourceBuilder<'a> {
+        self.cgroup.resources.cpu.update_values = true;
+        self.cgroup.resources.cpu.uclamp_max = Some(hundredths);
+        self
+    }
+
+    /// Limit the tasks to `cores` CPUs, e.g. `1.5`, by setting the CFS quota and period the way
+    /// container runtimes do. See `CpuController::set_cpu_limit()`.
+    pub fn cpus_limit(mut self, cores: f64) -> CpuResourceBuilder<'a> {
+        self.cgroup.resources.cpu.update_values = true;
+        self.cgroup.resources.cpu.quota =
+            Some(cpu::CpuQuota::from_cores(cores, cpu::DEFAULT_CFS_PERIOD));
+        self.cgroup.resources.cpu.period = Some(cpu::DEFAULT_CFS_PERIOD);
+        self
+    }
 
     /// Finish the construction of the cpu resources of a control group.
     pub fn done(self) -> CgroupBuilder<'a> {
@@ -309,8 +400,8 @@ impl<'a> BlkIoResourcesBuilder<'a> {
         self.cgroup.resources.blkio.weight_device.push(BlkIoDeviceResource {
             major,
             minor,
-            weight,
-            leaf_weight,
+            weight: Some(weight),
+            leaf_weight: Some(leaf_weight),
         });
         self
     }
@@ -366,3 +457,44 @@ impl<'a> BlkIoResourcesBuilder<'a> {
         self.cgroup
     }
 }
+
+/// A builder that configures the rdma controller of a control group.
+pub struct RdmaResourceBuilder<'a> {
+    cgroup: CgroupBuilder<'a>,
+}
+
+impl<'a> RdmaResourceBuilder<'a> {
+
+    /// Limit the number of HCA handles and/or HCA objects the tasks can use on `device`. `None`
+    /// leaves the respective limit untouched.
+    pub fn limit(mut self, device: String, hca_handles: Option<u32>, hca_objects: Option<u32>)
+        -> RdmaResourceBuilder<'a> {
+        self.cgroup.resources.rdma.update_values = true;
+        self.cgroup.resources.rdma.limits.push(RdmaResource {
+            device,
+            hca_handles,
+            hca_objects,
+        });
+        self
+    }
+
+    /// Finish the construction of the rdma resources of a control group.
+    pub fn done(self) -> CgroupBuilder<'a> {
+        self.cgroup
+    }
+}
+
+/// A builder that configures the freezer controller of a control group.
+pub struct FreezerResourceBuilder<'a> {
+    cgroup: CgroupBuilder<'a>,
+}
+
+impl<'a> FreezerResourceBuilder<'a> {
+
+    gen_setter!(freezer, FreezerController, freeze, state, freezer::FreezerState);
+
+    /// Finish the construction of the freezer resources of a control group.
+    pub fn done(self) -> CgroupBuilder<'a> {
+        self.cgroup
+    }
+}
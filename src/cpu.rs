@@ -3,9 +3,14 @@
 //! See the Kernel's documentation for more information about this subsystem, found at:
 //!  [Documentation/scheduler/sched-design-CFS.txt](https://www.kernel.org/doc/Documentation/scheduler/sched-design-CFS.txt)
 //!  paragraph 7 ("GROUP SCHEDULER EXTENSIONS TO CFS").
-use std::fs::File;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::*;
 
 use crate::error::*;
 use crate::error::ErrorKind::*;
@@ -17,21 +22,209 @@ use crate::{
 /// A controller that allows controlling the `cpu` subsystem of a Cgroup.
 ///
 /// In essence, it allows gathering information about how much the tasks inside the control group
-/// are using the CPU and creating rules that limit their usage. Note that this crate does not yet
-/// support managing realtime tasks.
+/// are using the CPU and creating rules that limit their usage, both for normal and realtime
+/// tasks.
+///
+/// In the unified hierarchy, the relative weight is set via `set_weight()` instead of
+/// `set_shares()` and the bandwidth via `set_max()` instead of the CFS quota and period. Realtime
+/// tasks cannot be managed there.
 #[derive(Debug, Clone)]
 pub struct CpuController {
     base: PathBuf,
     path: PathBuf,
+    v2: bool,
 }
 
 /// The current state of the control group and its processes.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
     /// Reports CPU time statistics.
     ///
     /// Corresponds the `cpu.stat` file in `cpu` control group.
-    pub stat: String,
+    pub stat: CpuStat,
+}
+
+/// The CPU time statistics of a control group, as found in `cpu.stat`.
+///
+/// Which of the fields are present depends on the hierarchy and the kernel version, hence they are
+/// all optional.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuStat {
+    /// The number of enforcement periods that have elapsed.
+    pub nr_periods: Option<u64>,
+    /// The number of periods in which the control group was throttled, because it exhausted its
+    /// quota.
+    pub nr_throttled: Option<u64>,
+    /// The total time the tasks of the control group were throttled for, in nanoseconds. Only
+    /// reported in the V1 hierarchy.
+    pub throttled_time: Option<u64>,
+    /// The total time the tasks of the control group were throttled for, in microseconds. Only
+    /// reported in the unified hierarchy.
+    pub throttled_usec: Option<u64>,
+    /// The total CPU time consumed by the tasks of the control group, in microseconds. Only
+    /// reported in the unified hierarchy.
+    pub usage_usec: Option<u64>,
+    /// The CPU time consumed in user mode, in microseconds. Only reported in the unified hierarchy.
+    pub user_usec: Option<u64>,
+    /// The CPU time consumed in kernel mode, in microseconds. Only reported in the unified
+    /// hierarchy.
+    pub system_usec: Option<u64>,
+    /// The number of periods in which the control group used more than its quota, by bursting.
+    pub nr_bursts: Option<u64>,
+    /// The total time spent above the quota by bursting, in nanoseconds. Only reported in the V1
+    /// hierarchy.
+    pub burst_time: Option<u64>,
+    /// The total time spent above the quota by bursting, in microseconds. Only reported in the
+    /// unified hierarchy.
+    pub burst_usec: Option<u64>,
+    /// Counters that are not known to this crate, by name.
+    pub extra: HashMap<String, u64>,
+}
+
+impl CpuStat {
+    /// Returns the fraction of the elapsed periods in which the control group was throttled, from
+    /// `0.0` to `1.0`.
+    ///
+    /// Returns `None` if no period has elapsed yet, e.g. because no quota is set.
+    pub fn throttle_ratio(&self) -> Option<f64> {
+        match (self.nr_periods, self.nr_throttled) {
+            (Some(periods), Some(throttled)) if periods > 0 => {
+                Some(throttled as f64 / periods as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_cpu_stat(s: String) -> Result<CpuStat> {
+    let mut stat = CpuStat::default();
+
+    for line in s.lines() {
+        let mut spl = line.split_whitespace();
+        let (key, value) = match (spl.next(), spl.next(), spl.next()) {
+            (Some(key), Some(value), None) => (key, value),
+            (None, _, _) => continue,
+            _ => return Err(Error::new(ParseError)),
+        };
+        let value = value
+            .parse::<u64>()
+            .map_err(|e| Error::with_cause(ParseError, e))?;
+
+        match key {
+            "nr_periods" => stat.nr_periods = Some(value),
+            "nr_throttled" => stat.nr_throttled = Some(value),
+            "throttled_time" => stat.throttled_time = Some(value),
+            "throttled_usec" => stat.throttled_usec = Some(value),
+            "usage_usec" => stat.usage_usec = Some(value),
+            "user_usec" => stat.user_usec = Some(value),
+            "system_usec" => stat.system_usec = Some(value),
+            "nr_bursts" => stat.nr_bursts = Some(value),
+            "burst_time" => stat.burst_time = Some(value),
+            "burst_usec" => stat.burst_usec = Some(value),
+            _ => {
+                stat.extra.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    Ok(stat)
+}
+
+/// The CFS period, in microseconds, that is used when a CPU limit is given in cores. This is the
+/// same period that container runtimes use.
+pub const DEFAULT_CFS_PERIOD: u64 = 100_000;
+
+/// The CFS quota of a control group: how long its tasks can run during one CFS period.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CpuQuota {
+    /// The tasks can run for the whole period. The kernel represents this as `-1` in the V1
+    /// hierarchy and as `"max"` in the unified hierarchy.
+    Unlimited,
+    /// The tasks can run for this many microseconds during one period.
+    Micros(u64),
+}
+
+/// Converts V1 `shares` (2 to 262144, 1024 by default) into a V2 `weight` (1 to 10000, 100 by
+/// default).
+///
+/// This is the linear mapping that container runtimes use, which maps the bounds of both ranges
+/// onto each other. Note that the defaults do not map onto each other: 1024 shares become a
+/// weight of 39.
+pub fn shares_to_weight(shares: u64) -> u64 {
+    let shares = shares.clamp(2, 262_144);
+    1 + ((shares - 2) * 9999) / 262_142
+}
+
+/// Converts a V2 `weight` into V1 `shares`, the inverse of `shares_to_weight()`.
+pub fn weight_to_shares(weight: u64) -> u64 {
+    let weight = weight.clamp(1, 10_000);
+    2 + ((weight - 1) * 262_142) / 9999
+}
+
+impl CpuQuota {
+    /// Computes the quota that limits the tasks to `cores` CPUs, e.g. `1.5`, during a period of
+    /// `period` microseconds.
+    ///
+    /// Negative values and NaN result in a quota of zero.
+    pub fn from_cores(cores: f64, period: u64) -> CpuQuota {
+        CpuQuota::Micros((cores * period as f64).round() as u64)
+    }
+}
+
+impl Default for CpuQuota {
+    /// By default, (as per the kernel) the quota is unlimited.
+    fn default() -> Self {
+        CpuQuota::Unlimited
+    }
+}
+
+impl From<i64> for CpuQuota {
+    /// Converts the V1 representation of a quota, where every negative value means unlimited.
+    fn from(us: i64) -> Self {
+        if us < 0 {
+            CpuQuota::Unlimited
+        } else {
+            CpuQuota::Micros(us as u64)
+        }
+    }
+}
+
+impl From<CpuQuota> for i64 {
+    /// Converts a quota into its V1 representation, i.e. `-1` if it is unlimited.
+    fn from(quota: CpuQuota) -> Self {
+        match quota {
+            CpuQuota::Unlimited => -1,
+            CpuQuota::Micros(us) => us as i64,
+        }
+    }
+}
+
+impl fmt::Display for CpuQuota {
+    /// Formats the quota as in the unified hierarchy's `cpu.max`, i.e. `"max"` if it is unlimited.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuQuota::Unlimited => write!(f, "max"),
+            CpuQuota::Micros(us) => write!(f, "{}", us),
+        }
+    }
+}
+
+impl FromStr for CpuQuota {
+    type Err = Error;
+
+    /// Parses a quota in either representation: `"max"`, `"-1"` or a number of microseconds.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "max" => Ok(CpuQuota::Unlimited),
+            s => s
+                .parse::<i64>()
+                .map(CpuQuota::from)
+                .map_err(|e| Error::with_cause(ParseError, e)),
+        }
+    }
 }
 
 impl ControllerInternal for CpuController {
@@ -51,28 +244,73 @@ impl ControllerInternal for CpuController {
         &self.base
     }
 
+    fn is_v2(&self) -> bool {
+        self.v2
+    }
+
     fn apply(&self, res: &Resources) -> Result<()> {
         // get the resources that apply to this controller
         let res: &CpuResources = &res.cpu;
 
+        if res.update_values && self.v2 {
+            return self.apply_v2(res);
+        }
+
         if res.update_values {
-            // apply pid_max
-            let _ = self.set_shares(res.shares);
-            if self.shares()? != res.shares as u64 {
-                return Err(Error::new(ErrorKind::Other));
+            if let Some(shares) = res.shares {
+                self.set_shares(shares)?;
+                if self.shares()? != shares {
+                    return Err(Error::new(ErrorKind::Other));
+                }
             }
 
-            let _ = self.set_cfs_period(res.period);
-            if self.cfs_period()? != res.period as u64 {
-                return Err(Error::new(ErrorKind::Other));
+            if res.quota.is_some() || res.period.is_some() {
+                let quota = match res.quota {
+                    Some(quota) => quota,
+                    None => self.cfs_quota()?,
+                };
+                let period = match res.period {
+                    Some(period) => period,
+                    None => self.cfs_period()?,
+                };
+                self.check_cfs_budget(quota, period)?;
             }
 
-            let _ = self.set_cfs_quota(res.quota as u64);
-            if self.cfs_quota()? != res.quota as u64 {
-                return Err(Error::new(ErrorKind::Other));
+            if let Some(period) = res.period {
+                self.set_cfs_period(period)?;
+                if self.cfs_period()? != period {
+                    return Err(Error::new(ErrorKind::Other));
+                }
+            }
+
+            // the kernel rejects a quota below the burst, so a burst that fits the old quota is
+            // written first, in case the quota is being lowered
+            let burst_first = match res.burst {
+                Some(burst) => {
+                    let old_quota = self.cfs_quota()?;
+                    check_burst(res.quota.unwrap_or(old_quota), burst)?;
+                    burst_fits(old_quota, burst)
+                }
+                None => false,
+            };
+            if let (true, Some(burst)) = (burst_first, res.burst) {
+                self.set_cfs_burst(burst)?;
             }
 
-            // TODO: rt properties (CONFIG_RT_GROUP_SCHED) are not yet supported
+            if let Some(quota) = res.quota {
+                self.set_cfs_quota(quota)?;
+                if self.cfs_quota()? != quota {
+                    return Err(Error::new(ErrorKind::Other));
+                }
+            }
+
+            if let (false, Some(burst)) = (burst_first, res.burst) {
+                self.set_cfs_burst(burst)?;
+            }
+
+            self.set_rt_sched(res.realtime_runtime, res.realtime_period)?;
+
+            self.apply_uclamp(res)?;
         }
 
         Ok(())
@@ -99,6 +337,108 @@ impl<'a> From<&'a Subsystem> for &'a CpuController {
     }
 }
 
+fn read_i64_from(mut file: File) -> Result<i64> {
+    let mut string = String::new();
+    match file.read_to_string(&mut string) {
+        Ok(_) => string.trim().parse().map_err(|e| Error::with_cause(ParseError, e)),
+        Err(e) => Err(Error::with_cause(ReadFailed, e)),
+    }
+}
+
+fn read_string_from(mut file: File) -> Result<String> {
+    let mut string = String::new();
+    match file.read_to_string(&mut string) {
+        Ok(_) => Ok(string.trim().to_string()),
+        Err(e) => Err(Error::with_cause(ReadFailed, e)),
+    }
+}
+
+/// Parse `cpu.max`, which contains the quota (or `"max"`) and the period, e.g. `"max 100000"`.
+fn parse_cpu_max(s: &str) -> Result<(CpuQuota, u64)> {
+    let mut spl = s.split_whitespace();
+    match (spl.next(), spl.next(), spl.next()) {
+        (Some(quota), Some(period), None) => Ok((
+            quota.parse()?,
+            period.parse().map_err(|e| Error::with_cause(ParseError, e))?,
+        )),
+        _ => Err(Error::new(ParseError)),
+    }
+}
+
+/// Parse `cpu.uclamp.min` or `cpu.uclamp.max`, which contain a percentage or `"max"`.
+fn parse_uclamp(s: &str) -> Result<f64> {
+    match s {
+        "max" => Ok(100.0),
+        s => s.parse().map_err(|e| Error::with_cause(ParseError, e)),
+    }
+}
+
+/// Whether the realtime period should be written before the runtime when going from the budget
+/// `(old_runtime, old_period)` to `(runtime, period)`.
+///
+/// The kernel rejects every intermediate budget in which the runtime exceeds the period, or which
+/// is larger than what is left of the parent's budget. Hence, the order that passes through the
+/// valid and smaller of the two intermediate budgets is chosen.
+fn rt_period_first(old_runtime: i64, old_period: u64, runtime: i64, period: u64) -> bool {
+    // -1 is unrestricted, i.e. larger than any runtime
+    let as_u128 = |runtime: i64| {
+        if runtime < 0 {
+            u128::from(u64::MAX)
+        } else {
+            runtime as u128
+        }
+    };
+    let (old_runtime, runtime) = (as_u128(old_runtime), as_u128(runtime));
+    let (old_period, period) = (u128::from(old_period), u128::from(period));
+
+    // period first passes through (old_runtime, period), runtime first through (runtime, old_period)
+    let period_first_valid = old_runtime <= period;
+    let runtime_first_valid = runtime <= old_period;
+    match (period_first_valid, runtime_first_valid) {
+        (true, false) => true,
+        (false, true) => false,
+        // old_runtime / period <= runtime / old_period
+        _ => old_runtime * old_period <= runtime * period,
+    }
+}
+
+/// Whether the kernel accepts `burst` along with `quota`, i.e. the burst is not larger than the
+/// quota.
+fn burst_fits(quota: CpuQuota, burst: u64) -> bool {
+    match quota {
+        CpuQuota::Unlimited => true,
+        CpuQuota::Micros(quota) => burst <= quota,
+    }
+}
+
+fn check_burst(quota: CpuQuota, burst: u64) -> Result<()> {
+    if burst_fits(quota, burst) {
+        Ok(())
+    } else {
+        Err(Error::new(InvalidResources(format!(
+            "CFS burst ({}) must not exceed the quota ({})",
+            burst, quota
+        ))))
+    }
+}
+
+/// Whether the bandwidth `quota / period` is at most `parent_quota / parent_period`.
+fn cfs_fits(quota: u64, period: u64, parent_quota: u64, parent_period: u64) -> bool {
+    u128::from(quota) * u128::from(parent_period) <= u128::from(parent_quota) * u128::from(period)
+}
+
+/// The realtime bandwidth `runtime / period` in the kernel's fixed point format, where `1 << 20`
+/// is the whole CPU. A runtime of `-1` (unlimited) counts as the whole CPU, like in `to_ratio()`.
+fn rt_ratio(runtime: i64, period: u64) -> u64 {
+    if runtime < 0 {
+        return 1 << 20;
+    }
+    if period == 0 {
+        return 0;
+    }
+    ((u128::from(runtime as u64) << 20) / u128::from(period)) as u64
+}
+
 fn read_u64_from(mut file: File) -> Result<u64> {
     let mut string = String::new();
     match file.read_to_string(&mut string) {
@@ -115,9 +455,65 @@ impl CpuController {
         Self {
             base: root.clone(),
             path: root,
+            v2: false,
         }
     }
 
+    /// Constructs a new `CpuController` for the unified hierarchy, with `root` being the root
+    /// directory of the hierarchy.
+    pub fn new_v2(root: PathBuf) -> Self {
+        Self {
+            base: root.clone(),
+            path: root,
+            v2: true,
+        }
+    }
+
+    fn apply_v2(&self, res: &CpuResources) -> Result<()> {
+        if res.realtime_runtime.is_some() || res.realtime_period.is_some() {
+            warn!("realtime scheduling is not supported in the unified hierarchy, ignoring");
+        }
+
+        if let Some(shares) = res.shares {
+            self.set_weight(shares_to_weight(shares))?;
+        }
+
+        let write_max = res.quota.is_some() || res.period.is_some();
+        if write_max || res.burst.is_some() {
+            let (old_quota, old_period) = self.max()?;
+            let quota = res.quota.unwrap_or(old_quota);
+            // same as in V1, see apply()
+            let burst_first = match res.burst {
+                Some(burst) => {
+                    check_burst(quota, burst)?;
+                    burst_fits(old_quota, burst)
+                }
+                None => false,
+            };
+            if let (true, Some(burst)) = (burst_first, res.burst) {
+                self.set_max_burst(burst)?;
+            }
+            if write_max {
+                self.set_max(quota, res.period.unwrap_or(old_period))?;
+            }
+            if let (false, Some(burst)) = (burst_first, res.burst) {
+                self.set_max_burst(burst)?;
+            }
+        }
+
+        self.apply_uclamp(res)
+    }
+
+    fn apply_uclamp(&self, res: &CpuResources) -> Result<()> {
+        if let Some(min) = res.uclamp_min {
+            self.set_uclamp_min(min as f64 / 100.0)?;
+        }
+        if let Some(max) = res.uclamp_max {
+            self.set_uclamp_max(max as f64 / 100.0)?;
+        }
+        Ok(())
+    }
+
     /// Returns CPU time statistics based on the processes in the control group.
     pub fn cpu(&self) -> Cpu {
         Cpu {
@@ -130,7 +526,9 @@ impl CpuController {
                         Ok(_) => Ok(s),
                         Err(e) => Err(Error::with_cause(ReadFailed, e)),
                     }
-                }).unwrap_or("".to_string()),
+                })
+                .and_then(parse_cpu_stat)
+                .unwrap_or_default(),
         }
     }
 
@@ -153,6 +551,43 @@ impl CpuController {
         self.open_path("cpu.shares", false).and_then(read_u64_from)
     }
 
+    /// Limit the tasks in this control group to `cores` CPUs, e.g. `1.5`.
+    ///
+    /// Like container runtimes do, this sets the period to `DEFAULT_CFS_PERIOD` and the quota to
+    /// `cores` times the period. As the kernel requires a quota of at least 1000 microseconds,
+    /// the smallest possible limit is `0.01` cores.
+    pub fn set_cpu_limit(&self, cores: f64) -> Result<()> {
+        let quota = CpuQuota::from_cores(cores, DEFAULT_CFS_PERIOD);
+        match quota {
+            CpuQuota::Micros(us) if cores.is_finite() && us >= 1000 => {}
+            _ => {
+                return Err(Error::new(InvalidResources(format!(
+                    "a CPU limit must be at least 0.01 cores, got {}",
+                    cores
+                ))))
+            }
+        }
+        if self.v2 {
+            return self.set_max(quota, DEFAULT_CFS_PERIOD);
+        }
+        self.set_cfs_period(DEFAULT_CFS_PERIOD)?;
+        self.set_cfs_quota(quota)
+    }
+
+    /// Retrieve the number of CPUs the tasks in this control group are limited to, computed from
+    /// the current quota and period. Returns `None` if the quota is unlimited.
+    pub fn cpu_limit(&self) -> Result<Option<f64>> {
+        let (quota, period) = if self.v2 {
+            self.max()?
+        } else {
+            (self.cfs_quota()?, self.cfs_period()?)
+        };
+        match quota {
+            CpuQuota::Unlimited => Ok(None),
+            CpuQuota::Micros(us) => Ok(Some(us as f64 / period as f64)),
+        }
+    }
+
     /// Specify a period (when using the CFS scheduler) of time in microseconds for how often this
     /// control group's access to the CPU should be reallocated.
     pub fn set_cfs_period(&self, us: u64) -> Result<()> {
@@ -172,18 +607,476 @@ impl CpuController {
 
     /// Specify a quota (when using the CFS scheduler) of time in microseconds for which all tasks
     /// in this control group can run during one period (see: `set_cfs_period()`).
-    pub fn set_cfs_quota(&self, us: u64) -> Result<()> {
+    pub fn set_cfs_quota(&self, quota: CpuQuota) -> Result<()> {
         self.open_path("cpu.cfs_quota_us", true)
             .and_then(|mut file| {
-                file.write_all(us.to_string().as_ref())
+                file.write_all(i64::from(quota).to_string().as_ref())
                     .map_err(|e| Error::with_cause(WriteFailed, e))
             })
     }
 
-    /// Retrieve the quota of time for which all tasks in this cgroup can run during one period, in
-    /// microseconds.
-    pub fn cfs_quota(&self) -> Result<u64> {
+    /// Retrieve the quota of time for which all tasks in this cgroup can run during one period.
+    pub fn cfs_quota(&self) -> Result<CpuQuota> {
         self.open_path("cpu.cfs_quota_us", false)
+            .and_then(read_i64_from)
+            .map(CpuQuota::from)
+    }
+
+    /// Specify how many microseconds the tasks in this control group can run above the quota
+    /// during one period, by using up the time they left unused in earlier periods. This is the
+    /// V1 equivalent of `set_max_burst()`.
+    ///
+    /// This requires a kernel with support for CFS bursts (5.14 or newer).
+    pub fn set_cfs_burst(&self, us: u64) -> Result<()> {
+        self.open_path("cpu.cfs_burst_us", true)
+            .and_then(|mut file| {
+                file.write_all(us.to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Retrieve how many microseconds the tasks in this control group can run above the quota
+    /// during one period.
+    pub fn cfs_burst(&self) -> Result<u64> {
+        self.open_path("cpu.cfs_burst_us", false)
+            .and_then(read_u64_from)
+    }
+
+    /// Specify the longest continuous period of time in microseconds for which realtime tasks in
+    /// this control group can run during one realtime period (see: `set_rt_period()`).
+    ///
+    /// `-1` removes the restriction. This requires a kernel with `CONFIG_RT_GROUP_SCHED`.
+    ///
+    /// The runtime of a control group is taken out of the budget of its parent, so the parent's
+    /// runtime has to be raised first.
+    pub fn set_rt_runtime(&self, us: i64) -> Result<()> {
+        self.open_path("cpu.rt_runtime_us", true)
+            .and_then(|mut file| {
+                file.write_all(us.to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Specify the period of time in microseconds after which the realtime runtime of this
+    /// control group is replenished.
+    pub fn set_rt_period(&self, us: u64) -> Result<()> {
+        self.open_path("cpu.rt_period_us", true)
+            .and_then(|mut file| {
+                file.write_all(us.to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Retrieve the longest continuous period of time in microseconds for which realtime tasks in
+    /// this control group can run during one realtime period. `-1` means there is no restriction.
+    pub fn rt_runtime(&self) -> Result<i64> {
+        self.open_path("cpu.rt_runtime_us", false)
+            .and_then(read_i64_from)
+    }
+
+    /// Retrieve the period of time in microseconds after which the realtime runtime of this
+    /// control group is replenished.
+    pub fn rt_period(&self) -> Result<u64> {
+        self.open_path("cpu.rt_period_us", false)
+            .and_then(read_u64_from)
+    }
+
+    /// Set the realtime runtime and period, in an order that the kernel accepts.
+    ///
+    /// Before anything is written, the new budget is checked against the budgets of the parent,
+    /// the siblings and the children of the control group, so that a budget the kernel would
+    /// refuse is reported as `ErrorKind::InvalidResources` instead of a failed write.
+    fn set_rt_sched(&self, runtime: Option<i64>, period: Option<u64>) -> Result<()> {
+        if runtime.is_none() && period.is_none() {
+            return Ok(());
+        }
+        let old_runtime = self.rt_runtime()?;
+        let old_period = self.rt_period()?;
+        let new_runtime = runtime.unwrap_or(old_runtime);
+        let new_period = period.unwrap_or(old_period);
+        self.check_rt_budget(new_runtime, new_period)?;
+
+        if rt_period_first(old_runtime, old_period, new_runtime, new_period) {
+            if let Some(period) = period {
+                self.set_rt_period(period)?;
+            }
+            if let Some(runtime) = runtime {
+                self.set_rt_runtime(runtime)?;
+            }
+        } else {
+            if let Some(runtime) = runtime {
+                self.set_rt_runtime(runtime)?;
+            }
+            if let Some(period) = period {
+                self.set_rt_period(period)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the relative weight of the control group in the unified hierarchy, from `1` to
+    /// `10000`, `100` by default. This is the V2 equivalent of `set_shares()`, see
+    /// `shares_to_weight()`.
+    pub fn set_weight(&self, weight: u64) -> Result<()> {
+        self.open_path("cpu.weight", true).and_then(|mut file| {
+            file.write_all(weight.to_string().as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve the relative weight of the control group in the unified hierarchy.
+    pub fn weight(&self) -> Result<u64> {
+        self.open_path("cpu.weight", false).and_then(read_u64_from)
+    }
+
+    /// Set the relative weight of the control group in the unified hierarchy as a nice value, from
+    /// `-20` to `19`. This is an alternative interface to `set_weight()`.
+    pub fn set_weight_nice(&self, nice: i64) -> Result<()> {
+        self.open_path("cpu.weight.nice", true).and_then(|mut file| {
+            file.write_all(nice.to_string().as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve the relative weight of the control group in the unified hierarchy as a nice value.
+    pub fn weight_nice(&self) -> Result<i64> {
+        self.open_path("cpu.weight.nice", false).and_then(read_i64_from)
+    }
+
+    /// Set the bandwidth of the control group in the unified hierarchy: the tasks can run for
+    /// `quota` during each `period` microseconds. This is the V2 equivalent of `set_cfs_quota()`
+    /// and `set_cfs_period()`.
+    pub fn set_max(&self, quota: CpuQuota, period: u64) -> Result<()> {
+        self.open_path("cpu.max", true).and_then(|mut file| {
+            file.write_all(format!("{} {}", quota, period).as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve the bandwidth of the control group in the unified hierarchy, as the quota and the
+    /// period in microseconds.
+    pub fn max(&self) -> Result<(CpuQuota, u64)> {
+        self.open_path("cpu.max", false)
+            .and_then(read_string_from)
+            .and_then(|s| parse_cpu_max(&s))
+    }
+
+    /// Set how many microseconds the tasks can run above the quota during one period, by using up
+    /// the time they left unused in earlier periods.
+    pub fn set_max_burst(&self, us: u64) -> Result<()> {
+        self.open_path("cpu.max.burst", true).and_then(|mut file| {
+            file.write_all(us.to_string().as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve how many microseconds the tasks can run above the quota during one period.
+    pub fn max_burst(&self) -> Result<u64> {
+        self.open_path("cpu.max.burst", false).and_then(read_u64_from)
+    }
+
+    /// If `idle` is true, the control group is scheduled like a task with `SCHED_IDLE`, i.e. only
+    /// when nothing else wants to run.
+    pub fn set_idle(&self, idle: bool) -> Result<()> {
+        self.open_path("cpu.idle", true).and_then(|mut file| {
+            file.write_all(if idle { b"1" } else { b"0" })
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Whether the control group is scheduled like a task with `SCHED_IDLE`.
+    pub fn idle(&self) -> Result<bool> {
+        self.open_path("cpu.idle", false)
             .and_then(read_u64_from)
+            .map(|idle| idle == 1)
+    }
+
+    /// Set the minimum utilization the scheduler assumes for the tasks, as a percentage from `0.0`
+    /// to `100.0`. This requires a kernel with `CONFIG_UCLAMP_TASK_GROUP`, in either hierarchy.
+    pub fn set_uclamp_min(&self, percent: f64) -> Result<()> {
+        self.open_path("cpu.uclamp.min", true).and_then(|mut file| {
+            file.write_all(format!("{:.2}", percent).as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve the minimum utilization the scheduler assumes for the tasks, as a percentage.
+    pub fn uclamp_min(&self) -> Result<f64> {
+        self.open_path("cpu.uclamp.min", false)
+            .and_then(read_string_from)
+            .and_then(|s| parse_uclamp(&s))
+    }
+
+    /// Set the maximum utilization the scheduler assumes for the tasks, as a percentage from `0.0`
+    /// to `100.0`. This requires a kernel with `CONFIG_UCLAMP_TASK_GROUP`, in either hierarchy.
+    pub fn set_uclamp_max(&self, percent: f64) -> Result<()> {
+        self.open_path("cpu.uclamp.max", true).and_then(|mut file| {
+            file.write_all(format!("{:.2}", percent).as_ref())
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Retrieve the maximum utilization the scheduler assumes for the tasks, as a percentage.
+    pub fn uclamp_max(&self) -> Result<f64> {
+        self.open_path("cpu.uclamp.max", false)
+            .and_then(read_string_from)
+            .and_then(|s| parse_uclamp(&s))
+    }
+
+    /// Checks that the CFS bandwidth `quota / period` does not exceed the bandwidth of the
+    /// closest ancestor that has a quota, which the kernel enforces in the V1 hierarchy.
+    fn check_cfs_budget(&self, quota: CpuQuota, period: u64) -> Result<()> {
+        let quota = match quota {
+            CpuQuota::Unlimited => return Ok(()),
+            CpuQuota::Micros(quota) => quota,
+        };
+        let mut ancestor = self.path.as_path();
+        while ancestor != self.base {
+            ancestor = match ancestor.parent() {
+                Some(parent) => parent,
+                None => return Ok(()),
+            };
+            let parent_quota = File::open(ancestor.join("cpu.cfs_quota_us"))
+                .map_err(|e| Error::with_cause(ReadFailed, e))
+                .and_then(read_i64_from)
+                .map(CpuQuota::from)?;
+            let parent_quota = match parent_quota {
+                CpuQuota::Unlimited => continue,
+                CpuQuota::Micros(parent_quota) => parent_quota,
+            };
+            let parent_period = File::open(ancestor.join("cpu.cfs_period_us"))
+                .map_err(|e| Error::with_cause(ReadFailed, e))
+                .and_then(read_u64_from)?;
+            if !cfs_fits(quota, period, parent_quota, parent_period) {
+                return Err(Error::new(InvalidResources(format!(
+                    "CFS bandwidth {}/{} exceeds the bandwidth of the parent ({}/{})",
+                    quota, period, parent_quota, parent_period
+                ))));
+            }
+            return Ok(());
+        }
+        Ok(())
+    }
+
+    /// Reads the realtime bandwidth of the control group in `dir`, see `rt_ratio()`.
+    fn rt_ratio_of(dir: &Path) -> Result<u64> {
+        let runtime = File::open(dir.join("cpu.rt_runtime_us"))
+            .map_err(|e| Error::with_cause(ReadFailed, e))
+            .and_then(read_i64_from)?;
+        let period = File::open(dir.join("cpu.rt_period_us"))
+            .map_err(|e| Error::with_cause(ReadFailed, e))
+            .and_then(read_u64_from)?;
+        Ok(rt_ratio(runtime, period))
+    }
+
+    /// Sums up the realtime bandwidth of the child control groups of `dir`, except `skip`.
+    fn rt_children_ratio(dir: &Path, skip: &Path) -> Result<u128> {
+        let mut sum = 0;
+        for entry in fs::read_dir(dir).map_err(|e| Error::with_cause(ReadFailed, e))? {
+            let path = entry.map_err(|e| Error::with_cause(ReadFailed, e))?.path();
+            if path.is_dir() && path != skip {
+                sum += u128::from(CpuController::rt_ratio_of(&path)?);
+            }
+        }
+        Ok(sum)
+    }
+
+    /// Checks the realtime budget `runtime / period` the way the kernel does: together with the
+    /// budgets of the sibling control groups, it must fit into the budget of the parent, and it
+    /// must leave room for the budgets of the child control groups.
+    fn check_rt_budget(&self, runtime: i64, period: u64) -> Result<()> {
+        let ratio = rt_ratio(runtime, period);
+        let children = CpuController::rt_children_ratio(&self.path, &self.path)?;
+        if children > u128::from(ratio) {
+            return Err(Error::new(InvalidResources(format!(
+                "realtime budget {}/{} is smaller than the budgets of the child control groups",
+                runtime, period
+            ))));
+        }
+
+        if self.path == self.base {
+            return Ok(());
+        }
+        let parent = match self.path.parent() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let parent_ratio = CpuController::rt_ratio_of(parent)?;
+        let siblings = CpuController::rt_children_ratio(parent, &self.path)?;
+        if siblings + u128::from(ratio) > u128::from(parent_ratio) {
+            return Err(Error::new(InvalidResources(format!(
+                "realtime budget {}/{} and the budgets of the sibling control groups exceed the \
+                 budget of the parent",
+                runtime, period
+            ))));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cpu::{
+        burst_fits, cfs_fits, parse_cpu_max, parse_cpu_stat, parse_uclamp, rt_period_first,
+        rt_ratio, shares_to_weight, weight_to_shares, CpuQuota, CpuStat,
+    };
+
+    static GOOD_V1_VAL: &str = "\
+nr_periods 200
+nr_throttled 50
+throttled_time 123456789
+nr_bursts 0
+burst_time 0
+";
+
+    static GOOD_V2_VAL: &str = "\
+usage_usec 8254154
+user_usec 5640318
+system_usec 2613836
+core_sched.force_idle_usec 0
+nr_periods 0
+nr_throttled 0
+throttled_usec 0
+nr_bursts 0
+burst_usec 0
+";
+
+    #[test]
+    fn test_parse_cpu_stat_v1() {
+        let ok = parse_cpu_stat(GOOD_V1_VAL.to_string()).unwrap();
+        assert_eq!(
+            ok,
+            CpuStat {
+                nr_periods: Some(200),
+                nr_throttled: Some(50),
+                throttled_time: Some(123456789),
+                nr_bursts: Some(0),
+                burst_time: Some(0),
+                ..Default::default()
+            }
+        );
+        assert_eq!(ok.throttle_ratio(), Some(0.25));
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_v2() {
+        let ok = parse_cpu_stat(GOOD_V2_VAL.to_string()).unwrap();
+        let mut extra = HashMap::new();
+        extra.insert("core_sched.force_idle_usec".to_string(), 0);
+        assert_eq!(
+            ok,
+            CpuStat {
+                nr_periods: Some(0),
+                nr_throttled: Some(0),
+                throttled_usec: Some(0),
+                usage_usec: Some(8254154),
+                user_usec: Some(5640318),
+                system_usec: Some(2613836),
+                nr_bursts: Some(0),
+                burst_usec: Some(0),
+                extra,
+                ..Default::default()
+            }
+        );
+        // no period has elapsed
+        assert_eq!(ok.throttle_ratio(), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_bad() {
+        assert!(parse_cpu_stat("nr_periods\n".to_string()).is_err());
+        assert!(parse_cpu_stat("nr_periods x\n".to_string()).is_err());
+        assert_eq!(CpuStat::default().throttle_ratio(), None);
+    }
+
+    #[test]
+    fn test_rt_period_first() {
+        // shrinking the period below the old runtime needs the runtime to go first
+        assert!(!rt_period_first(950_000, 1_000_000, 10_000, 100_000));
+        // growing the runtime above the old period needs the period to go first
+        assert!(rt_period_first(10_000, 100_000, 500_000, 1_000_000));
+        // both orders are valid: take the one with the smaller intermediate budget
+        assert!(!rt_period_first(40_000, 100_000, 20_000, 50_000));
+        assert!(rt_period_first(50_000, 100_000, 50_000, 200_000));
+        assert!(!rt_period_first(-1, 1_000_000, 10_000, 100_000));
+    }
+
+    #[test]
+    fn test_burst_fits() {
+        assert!(burst_fits(CpuQuota::Unlimited, u64::MAX));
+        assert!(burst_fits(CpuQuota::Micros(50_000), 50_000));
+        assert!(!burst_fits(CpuQuota::Micros(50_000), 50_001));
+    }
+
+    #[test]
+    fn test_cfs_fits() {
+        assert!(cfs_fits(50_000, 100_000, 50_000, 100_000));
+        assert!(cfs_fits(20_000, 50_000, 50_000, 100_000));
+        assert!(!cfs_fits(80_000, 100_000, 50_000, 100_000));
+        // a shorter period with the same quota is a larger share of the CPU
+        assert!(!cfs_fits(50_000, 50_000, 50_000, 100_000));
+        assert!(cfs_fits(u64::MAX, u64::MAX, 1, 1));
+    }
+
+    #[test]
+    fn test_rt_ratio() {
+        assert_eq!(rt_ratio(-1, 1_000_000), 1 << 20);
+        assert_eq!(rt_ratio(1_000_000, 1_000_000), 1 << 20);
+        assert_eq!(rt_ratio(100_000, 1_000_000), (1 << 20) / 10);
+        assert_eq!(rt_ratio(0, 1_000_000), 0);
+        assert_eq!(rt_ratio(5_000, 0), 0);
+        // two children at 6% do not fit into 10%
+        assert!(2 * rt_ratio(6_000, 100_000) > rt_ratio(10_000, 100_000));
+    }
+
+    #[test]
+    fn test_cpu_quota() {
+        assert_eq!("max".parse::<CpuQuota>().unwrap(), CpuQuota::Unlimited);
+        assert_eq!("-1\n".parse::<CpuQuota>().unwrap(), CpuQuota::Unlimited);
+        assert_eq!("50000".parse::<CpuQuota>().unwrap(), CpuQuota::Micros(50000));
+        assert!("x".parse::<CpuQuota>().is_err());
+
+        assert_eq!(i64::from(CpuQuota::Unlimited), -1);
+        assert_eq!(i64::from(CpuQuota::Micros(50000)), 50000);
+        assert_eq!(CpuQuota::Unlimited.to_string(), "max");
+        assert_eq!(CpuQuota::Micros(50000).to_string(), "50000");
+
+        assert_eq!(CpuQuota::from_cores(1.5, 100_000), CpuQuota::Micros(150_000));
+        assert_eq!(CpuQuota::from_cores(0.333, 100_000), CpuQuota::Micros(33_300));
+        assert_eq!(CpuQuota::from_cores(-1.0, 100_000), CpuQuota::Micros(0));
+    }
+
+    #[test]
+    fn test_parse_cpu_max() {
+        assert_eq!(parse_cpu_max("max 100000").unwrap(), (CpuQuota::Unlimited, 100000));
+        assert_eq!(
+            parse_cpu_max("50000 100000").unwrap(),
+            (CpuQuota::Micros(50000), 100000)
+        );
+        assert!(parse_cpu_max("max").is_err());
+        assert!(parse_cpu_max("max x").is_err());
+    }
+
+    #[test]
+    fn test_parse_uclamp() {
+        assert_eq!(parse_uclamp("max").unwrap(), 100.0);
+        assert_eq!(parse_uclamp("12.50").unwrap(), 12.5);
+        assert!(parse_uclamp("x").is_err());
+    }
+
+    #[test]
+    fn test_shares_weight_conversion() {
+        assert_eq!(shares_to_weight(2), 1);
+        assert_eq!(shares_to_weight(1024), 39);
+        assert_eq!(shares_to_weight(262_144), 10_000);
+        // out of range values are clamped
+        assert_eq!(shares_to_weight(0), 1);
+        assert_eq!(shares_to_weight(1_000_000), 10_000);
+
+        assert_eq!(weight_to_shares(1), 2);
+        assert_eq!(weight_to_shares(10_000), 262_144);
+        assert_eq!(weight_to_shares(100), 2597);
     }
 }
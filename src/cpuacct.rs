@@ -5,6 +5,7 @@
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::error::*;
 use crate::error::ErrorKind::*;
@@ -22,9 +23,10 @@ pub struct CpuAcctController {
 }
 
 /// Represents the statistics retrieved from the control group.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CpuAcct {
     /// Divides the time used by the tasks into `user` time and `system` time.
-    pub stat: String,
+    pub stat: CpuAcctStat,
     /// Total CPU time (in nanoseconds) spent by the tasks.
     pub usage: u64,
     /// Total CPU time (in nanoseconds) spent by the tasks, broken down by CPU and by whether the
@@ -38,20 +40,112 @@ pub struct CpuAcct {
     /// 2 8598185449 0
     /// 3 8648262473 0
     /// ```
-    pub usage_all: String,
-    /// CPU time (in nanoseconds) spent by the tasks, broken down by each CPU.
-    /// Times spent in each CPU are separated by a space.
-    pub usage_percpu: String,
+    pub usage_all: Vec<CpuUsage>,
+    /// CPU time (in nanoseconds) spent by the tasks, broken down by each CPU, indexed by the
+    /// number of the CPU.
+    pub usage_percpu: Vec<u64>,
     /// As for `usage_percpu`, but the `system` time spent.
-    pub usage_percpu_sys: String,
+    pub usage_percpu_sys: Vec<u64>,
     /// As for `usage_percpu`, but the `user` time spent.
-    pub usage_percpu_user: String,
+    pub usage_percpu_user: Vec<u64>,
     /// CPU time (in nanoseconds) spent by the tasks that counted for `system` time.
     pub usage_sys: u64,
     /// CPU time (in nanoseconds) spent by the tasks that counted for `user` time.
     pub usage_user: u64,
 }
 
+/// The CPU time used by the tasks, divided into `user` time and `system` time, as found in
+/// `cpuacct.stat`.
+///
+/// The times are in clock ticks (`USER_HZ`), see `user_time()` and `system_time()` for the
+/// conversion.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuAcctStat {
+    /// The time spent in user mode, in clock ticks.
+    pub user: u64,
+    /// The time spent in kernel mode, in clock ticks.
+    pub system: u64,
+}
+
+impl CpuAcctStat {
+    /// The time spent in user mode.
+    pub fn user_time(&self) -> Duration {
+        ticks_to_duration(self.user)
+    }
+
+    /// The time spent in kernel mode.
+    pub fn system_time(&self) -> Duration {
+        ticks_to_duration(self.system)
+    }
+}
+
+/// The CPU time spent by the tasks on a single CPU, as found in `cpuacct.usage_all`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuUsage {
+    /// The number of the CPU.
+    pub cpu: u64,
+    /// The time spent in user mode, in nanoseconds.
+    pub user: u64,
+    /// The time spent in kernel mode, in nanoseconds.
+    pub system: u64,
+}
+
+/// Converts clock ticks, as used by `cpuacct.stat`, into a `Duration`.
+fn ticks_to_duration(ticks: u64) -> Duration {
+    let hz = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        hz if hz > 0 => hz as u64,
+        // the value `USER_HZ` has on virtually all systems
+        _ => 100,
+    };
+    Duration::from_secs(ticks / hz) + Duration::from_nanos((ticks % hz) * 1_000_000_000 / hz)
+}
+
+fn parse_stat(s: String) -> Result<CpuAcctStat> {
+    let mut stat = CpuAcctStat::default();
+    for line in s.lines() {
+        let mut spl = line.split_whitespace();
+        let (key, value) = match (spl.next(), spl.next(), spl.next()) {
+            (Some(key), Some(value), None) => (key, value),
+            (None, _, _) => continue,
+            _ => return Err(Error::new(ParseError)),
+        };
+        let value = value
+            .parse::<u64>()
+            .map_err(|e| Error::with_cause(ParseError, e))?;
+        match key {
+            "user" => stat.user = value,
+            "system" => stat.system = value,
+            _ => {}
+        }
+    }
+    Ok(stat)
+}
+
+fn parse_usage_all(s: String) -> Result<Vec<CpuUsage>> {
+    let mut fin = Vec::new();
+    // the first line is the header: "cpu user system"
+    for line in s.lines().skip(1) {
+        let spl = line
+            .split_whitespace()
+            .map(|v| v.parse::<u64>().map_err(|e| Error::with_cause(ParseError, e)))
+            .collect::<Result<Vec<_>>>()?;
+        match spl[..] {
+            [cpu, user, system] => fin.push(CpuUsage { cpu, user, system }),
+            [] => continue,
+            _ => return Err(Error::new(ParseError)),
+        }
+    }
+    Ok(fin)
+}
+
+fn parse_percpu(s: String) -> Result<Vec<u64>> {
+    s.split_whitespace()
+        .map(|v| v.parse::<u64>().map_err(|e| Error::with_cause(ParseError, e)))
+        .collect()
+}
+
 impl ControllerInternal for CpuAcctController {
     fn control_type(&self) -> Controllers {
         Controllers::CpuAcct
@@ -128,7 +222,8 @@ impl CpuAcctController {
             stat: self
                 .open_path("cpuacct.stat", false)
                 .and_then(|file| read_string_from(file))
-                .unwrap_or("".to_string()),
+                .and_then(parse_stat)
+                .unwrap_or_default(),
             usage: self
                 .open_path("cpuacct.usage", false)
                 .and_then(|file| read_u64_from(file))
@@ -136,19 +231,23 @@ impl CpuAcctController {
             usage_all: self
                 .open_path("cpuacct.usage_all", false)
                 .and_then(|file| read_string_from(file))
-                .unwrap_or("".to_string()),
+                .and_then(parse_usage_all)
+                .unwrap_or_default(),
             usage_percpu: self
                 .open_path("cpuacct.usage_percpu", false)
                 .and_then(|file| read_string_from(file))
-                .unwrap_or("".to_string()),
+                .and_then(parse_percpu)
+                .unwrap_or_default(),
             usage_percpu_sys: self
                 .open_path("cpuacct.usage_percpu_sys", false)
                 .and_then(|file| read_string_from(file))
-                .unwrap_or("".to_string()),
+                .and_then(parse_percpu)
+                .unwrap_or_default(),
             usage_percpu_user: self
                 .open_path("cpuacct.usage_percpu_user", false)
                 .and_then(|file| read_string_from(file))
-                .unwrap_or("".to_string()),
+                .and_then(parse_percpu)
+                .unwrap_or_default(),
             usage_sys: self
                 .open_path("cpuacct.usage_sys", false)
                 .and_then(|file| read_u64_from(file))
@@ -166,3 +265,65 @@ impl CpuAcctController {
             .and_then(|mut file| file.write_all(b"0").map_err(|e| Error::with_cause(WriteFailed, e)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::cpuacct::{
+        parse_percpu, parse_stat, parse_usage_all, ticks_to_duration, CpuAcctStat, CpuUsage,
+    };
+
+    #[test]
+    fn test_parse_stat() {
+        let ok = parse_stat("user 42678\nsystem 6368\n".to_string()).unwrap();
+        assert_eq!(
+            ok,
+            CpuAcctStat {
+                user: 42678,
+                system: 6368,
+            }
+        );
+        assert!(parse_stat("user\n".to_string()).is_err());
+        assert!(parse_stat("user x\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_usage_all() {
+        let usage_all = "cpu user system\n0 8348363768 0\n1 8324369100 12\n";
+        let ok = parse_usage_all(usage_all.to_string()).unwrap();
+        assert_eq!(
+            ok,
+            vec![
+                CpuUsage {
+                    cpu: 0,
+                    user: 8348363768,
+                    system: 0,
+                },
+                CpuUsage {
+                    cpu: 1,
+                    user: 8324369100,
+                    system: 12,
+                },
+            ]
+        );
+        assert!(parse_usage_all("cpu user system\n0 1\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_percpu() {
+        // the kernel leaves a trailing space
+        let ok = parse_percpu("490466462360 0 1234 ".to_string()).unwrap();
+        assert_eq!(ok, vec![490466462360, 0, 1234]);
+        assert!(parse_percpu("".to_string()).unwrap().is_empty());
+        assert!(parse_percpu("1 x".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_ticks_to_duration() {
+        let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as u64;
+        assert_eq!(ticks_to_duration(0), Duration::from_secs(0));
+        assert_eq!(ticks_to_duration(3 * hz), Duration::from_secs(3));
+        assert_eq!(ticks_to_duration(hz / 2), Duration::from_millis(500));
+    }
+}
@@ -2,9 +2,16 @@
 //!
 //! See the Kernel's documentation for more information about this subsystem, found at:
 //!  [Documentation/cgroup-v1/cpusets.txt](https://www.kernel.org/doc/Documentation/cgroup-v1/cpusets.txt)
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::iter::FromIterator;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use log::*;
 
 use crate::error::*;
 use crate::error::ErrorKind::*;
@@ -21,25 +28,212 @@ use crate::{
 pub struct CpuSetController {
     base: PathBuf,
     path: PathBuf,
+    v2: bool,
+}
+
+/// A set of CPUs or memory nodes, identified by their IDs.
+///
+/// It is parsed from and formatted as the list format the kernel uses in files like
+/// `cpuset.cpus`, e.g. `"0-3,8,10-11"`.
+///
+/// ## Example:
+///
+/// ```
+/// # use cgroups::cpuset::CpuList;
+/// let cpus: CpuList = "0-3,8".parse().unwrap();
+/// let busy: CpuList = "2-3".parse().unwrap();
+/// assert_eq!(cpus.difference(&busy).to_string(), "0-1,8");
+/// assert_eq!(cpus.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 8]);
+/// ```
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct CpuList {
+    ids: BTreeSet<u64>,
+}
+
+impl CpuList {
+    /// The largest ID that is accepted when parsing a list.
+    ///
+    /// The kernel supports at most a few thousand CPUs and memory nodes, so this is far above any
+    /// real ID, but keeps a range like `"0-4294967295"` from being expanded into billions of IDs.
+    pub const MAX_ID: u64 = 65_535;
+
+    /// Constructs an empty `CpuList`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the list contains no IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the number of IDs in the list.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns true if `id` is part of the list.
+    pub fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+    }
+
+    /// Adds `id` to the list. Returns false if it was already present.
+    pub fn insert(&mut self, id: u64) -> bool {
+        self.ids.insert(id)
+    }
+
+    /// Removes `id` from the list. Returns false if it was not present.
+    pub fn remove(&mut self, id: u64) -> bool {
+        self.ids.remove(&id)
+    }
+
+    /// Iterates over the IDs in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.ids.iter().cloned()
+    }
+
+    /// Returns the IDs as a list of `(start, end)` ranges, where both sides are inclusive.
+    pub fn ranges(&self) -> Vec<(u64, u64)> {
+        let mut fin: Vec<(u64, u64)> = Vec::new();
+        for id in self.iter() {
+            match fin.last_mut() {
+                Some(last) if last.1 + 1 == id => last.1 = id,
+                _ => fin.push((id, id)),
+            }
+        }
+        fin
+    }
+
+    /// Returns the IDs that are in `self`, `other` or both.
+    pub fn union(&self, other: &CpuList) -> CpuList {
+        self.ids.union(&other.ids).cloned().collect()
+    }
+
+    /// Returns the IDs that are in both `self` and `other`.
+    pub fn intersection(&self, other: &CpuList) -> CpuList {
+        self.ids.intersection(&other.ids).cloned().collect()
+    }
+
+    /// Returns the IDs that are in `self`, but not in `other`.
+    pub fn difference(&self, other: &CpuList) -> CpuList {
+        self.ids.difference(&other.ids).cloned().collect()
+    }
+
+    /// Returns true if all IDs of `self` are also in `other`.
+    pub fn is_subset(&self, other: &CpuList) -> bool {
+        self.ids.is_subset(&other.ids)
+    }
+
+    /// Converts the list into a `cpu_set_t`, as used by `sched_setaffinity(2)`.
+    ///
+    /// Fails with `InvalidOperation` if an ID does not fit into a `cpu_set_t`, i.e. is at least
+    /// `CPU_SETSIZE`.
+    pub fn to_cpu_set(&self) -> Result<libc::cpu_set_t> {
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for id in self.iter() {
+            if id >= libc::CPU_SETSIZE as u64 {
+                return Err(Error::new(InvalidOperation));
+            }
+            unsafe { libc::CPU_SET(id as usize, &mut set) };
+        }
+        Ok(set)
+    }
+
+    /// Constructs a list from the CPUs that are part of `set`.
+    pub fn from_cpu_set(set: &libc::cpu_set_t) -> Self {
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&id| unsafe { libc::CPU_ISSET(id, set) })
+            .map(|id| id as u64)
+            .collect()
+    }
+}
+
+impl FromStr for CpuList {
+    type Err = Error;
+
+    /// Parses the list format, e.g. `"0-3,8"`. Whitespace and newlines around the IDs are ignored.
+    ///
+    /// IDs above `CpuList::MAX_ID` are rejected.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ids = BTreeSet::new();
+        for (start, end) in parse_range(s.to_string())? {
+            if start > end || end > CpuList::MAX_ID {
+                return Err(Error::new(ParseError));
+            }
+            ids.extend(start..=end);
+        }
+        Ok(CpuList { ids })
+    }
+}
+
+impl fmt::Display for CpuList {
+    /// Formats the list in the kernel's list format, e.g. `"0-3,8"`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (start, end)) in self.ranges().into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<CpuList> for String {
+    fn from(list: CpuList) -> String {
+        list.to_string()
+    }
+}
+
+impl TryFrom<String> for CpuList {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl FromIterator<u64> for CpuList {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        CpuList {
+            ids: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuList {
+    type Item = u64;
+    type IntoIter = std::iter::Cloned<std::collections::btree_set::Iter<'a, u64>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ids.iter().cloned()
+    }
 }
 
 /// The current state of the `cpuset` controller for this control group.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CpuSet {
     /// If true, no other control groups can share the CPUs listed in the `cpus` field.
     pub cpu_exclusive: bool,
     /// The list of CPUs the tasks of the control group can run on.
-    ///
-    /// This is a vector of `(start, end)` tuples, where each tuple is a range of CPUs where the
-    /// control group is allowed to run on. Both sides of the range are inclusive.
-    pub cpus: Vec<(u64, u64)>,
+    pub cpus: CpuList,
     /// The list of CPUs that the tasks can effectively run on. This removes the list of CPUs that
     /// the parent (and all of its parents) cannot run on from the `cpus` field of this control
     /// group.
-    pub effective_cpus: Vec<(u64, u64)>,
+    pub effective_cpus: CpuList,
     /// The list of memory nodes that the tasks can effectively use. This removes the list of nodes that
     /// the parent (and all of its parents) cannot use from the `mems` field of this control
     /// group.
-    pub effective_mems: Vec<(u64, u64)>,
+    pub effective_mems: CpuList,
     /// If true, no other control groups can share the memory nodes listed in the `mems` field.
     pub mem_exclusive: bool,
     /// If true, the control group is 'hardwalled'. Kernel memory allocations (except for a few
@@ -59,9 +253,7 @@ pub struct CpuSet {
     /// specified in `mems`.
     pub memory_spread_slab: bool,
     /// The list of memory nodes the tasks of the control group can use.
-    ///
-    /// The format is the same as the `cpus`, `effective_cpus` and `effective_mems` fields.
-    pub mems: Vec<(u64, u64)>,
+    pub mems: CpuList,
     /// If true, the kernel will attempt to rebalance the load between the CPUs specified in the
     /// `cpus` field of this control group.
     pub sched_load_balance: bool,
@@ -92,14 +284,49 @@ impl ControllerInternal for CpuSetController {
     fn get_base(&self) -> &PathBuf {
         &self.base
     }
+    fn is_v2(&self) -> bool {
+        self.v2
+    }
 
     fn apply(&self, res: &Resources) -> Result<()> {
         // get the resources that apply to this controller
         let res: &CpuResources = &res.cpu;
 
+        if res.update_values && self.v2 {
+            return self.apply_v2(res);
+        }
+
         if res.update_values {
-            let _ = self.set_cpus(&res.cpus);
-            let _ = self.set_mems(&res.mems);
+            if let Some(cpus) = &res.cpus {
+                self.set_cpus(cpus)?;
+            }
+            if let Some(mems) = &res.mems {
+                self.set_mems(mems)?;
+            }
+            if let Some(b) = res.cpu_exclusive {
+                self.set_cpu_exclusive(b)?;
+            }
+            if let Some(b) = res.mem_exclusive {
+                self.set_mem_exclusive(b)?;
+            }
+            if let Some(b) = res.mem_hardwall {
+                self.set_hardwall(b)?;
+            }
+            if let Some(b) = res.memory_migrate {
+                self.set_memory_migration(b)?;
+            }
+            if let Some(b) = res.memory_spread_page {
+                self.set_memory_spread_page(b)?;
+            }
+            if let Some(b) = res.memory_spread_slab {
+                self.set_memory_spread_slab(b)?;
+            }
+            if let Some(b) = res.sched_load_balance {
+                self.set_load_balancing(b)?;
+            }
+            if let Some(i) = res.sched_relax_domain_level {
+                self.set_rebalance_relax_domain_level(i)?;
+            }
         }
 
         Ok(())
@@ -143,15 +370,18 @@ fn read_u64_from(mut file: File) -> Result<u64> {
 }
 
 /// Parse a string like "1,2,4-5,8" into a list of (start, end) tuples.
+///
+/// Whitespace and newlines around the numbers are ignored.
 fn parse_range(s: String) -> Result<Vec<(u64, u64)>> {
     let mut fin = Vec::new();
 
-    if s == "".to_string() {
+    let s = s.trim();
+    if s.is_empty() {
         return Ok(fin);
     }
 
     // first split by commas
-    let comma_split = s.split(",");
+    let comma_split = s.split(',').map(|sp| sp.trim());
 
     for sp in comma_split {
         if sp.contains("-") {
@@ -160,8 +390,8 @@ fn parse_range(s: String) -> Result<Vec<(u64, u64)>> {
             if dash_split.len() != 2 {
                 return Err(Error::new(ParseError));
             }
-            let first = dash_split[0].parse::<u64>();
-            let second = dash_split[1].parse::<u64>();
+            let first = dash_split[0].trim().parse::<u64>();
+            let second = dash_split[1].trim().parse::<u64>();
             if first.is_err() || second.is_err() {
                 return Err(Error::new(ParseError));
             }
@@ -180,6 +410,32 @@ fn parse_range(s: String) -> Result<Vec<(u64, u64)>> {
 }
 
 impl CpuSetController {
+    fn apply_v2(&self, res: &CpuResources) -> Result<()> {
+        let unsupported = [
+            ("cpu_exclusive", res.cpu_exclusive.is_some()),
+            ("mem_exclusive", res.mem_exclusive.is_some()),
+            ("mem_hardwall", res.mem_hardwall.is_some()),
+            ("memory_migrate", res.memory_migrate.is_some()),
+            ("memory_spread_page", res.memory_spread_page.is_some()),
+            ("memory_spread_slab", res.memory_spread_slab.is_some()),
+            ("sched_load_balance", res.sched_load_balance.is_some()),
+            ("sched_relax_domain_level", res.sched_relax_domain_level.is_some()),
+        ];
+        for &(name, set) in unsupported.iter() {
+            if set {
+                warn!("{} is not supported in the unified hierarchy, ignoring", name);
+            }
+        }
+
+        if let Some(cpus) = &res.cpus {
+            self.set_cpus(cpus)?;
+        }
+        if let Some(mems) = &res.mems {
+            self.set_mems(mems)?;
+        }
+        Ok(())
+    }
+
     /// Contructs a new `CpuSetController` with `oroot` serving as the root of the control group.
     pub fn new(oroot: PathBuf) -> Self {
         let mut root = oroot;
@@ -187,12 +443,28 @@ impl CpuSetController {
         Self {
             base: root.clone(),
             path: root,
+            v2: false,
+        }
+    }
+
+    /// Constructs a new `CpuSetController` for the unified hierarchy, with `root` being the root directory
+    /// of the hierarchy.
+    pub fn new_v2(root: PathBuf) -> Self {
+        Self {
+            base: root.clone(),
+            path: root,
+            v2: true,
         }
     }
 
     /// Returns the statistics gathered by the kernel for this control group. See the struct for
     /// more information on what information this entails.
     pub fn cpuset(&self) -> CpuSet {
+        let (effective_cpus, effective_mems) = if self.v2 {
+            ("cpuset.cpus.effective", "cpuset.mems.effective")
+        } else {
+            ("cpuset.effective_cpus", "cpuset.effective_mems")
+        };
         CpuSet {
             cpu_exclusive: {
                 self.open_path("cpuset.cpu_exclusive", false)
@@ -203,20 +475,20 @@ impl CpuSetController {
             cpus: {
                 self.open_path("cpuset.cpus", false)
                     .and_then(read_string_from)
-                    .and_then(parse_range)
-                    .unwrap_or(Vec::new())
+                    .and_then(|s| s.parse())
+                    .unwrap_or_default()
             },
             effective_cpus: {
-                self.open_path("cpuset.effective_cpus", false)
+                self.open_path(effective_cpus, false)
                     .and_then(read_string_from)
-                    .and_then(parse_range)
-                    .unwrap_or(Vec::new())
+                    .and_then(|s| s.parse())
+                    .unwrap_or_default()
             },
             effective_mems: {
-                self.open_path("cpuset.effective_mems", false)
+                self.open_path(effective_mems, false)
                     .and_then(read_string_from)
-                    .and_then(parse_range)
-                    .unwrap_or(Vec::new())
+                    .and_then(|s| s.parse())
+                    .unwrap_or_default()
             },
             mem_exclusive: {
                 self.open_path("cpuset.mem_exclusive", false)
@@ -262,8 +534,8 @@ impl CpuSetController {
             mems: {
                 self.open_path("cpuset.mems", false)
                     .and_then(read_string_from)
-                    .and_then(parse_range)
-                    .unwrap_or(Vec::new())
+                    .and_then(|s| s.parse())
+                    .unwrap_or_default()
             },
             sched_load_balance: {
                 self.open_path("cpuset.sched_load_balance", false)
@@ -306,22 +578,17 @@ impl CpuSetController {
     }
 
     /// Set the CPUs that the tasks in this control group can run on.
-    ///
-    /// Syntax is a comma separated list of CPUs, with an additional extension that ranges can
-    /// be represented via dashes.
-    pub fn set_cpus(&self, cpus: &str) -> Result<()> {
+    pub fn set_cpus(&self, cpus: &CpuList) -> Result<()> {
         self.open_path("cpuset.cpus", true).and_then(|mut file| {
-            file.write_all(cpus.as_ref())
+            file.write_all(cpus.to_string().as_ref())
                 .map_err(|e| Error::with_cause(WriteFailed, e))
         })
     }
 
     /// Set the memory nodes that the tasks in this control group can use.
-    ///
-    /// Syntax is the same as with `set_cpus()`.
-    pub fn set_mems(&self, mems: &str) -> Result<()> {
+    pub fn set_mems(&self, mems: &CpuList) -> Result<()> {
         self.open_path("cpuset.mems", true).and_then(|mut file| {
-            file.write_all(mems.as_ref())
+            file.write_all(mems.to_string().as_ref())
                 .map_err(|e| Error::with_cause(WriteFailed, e))
         })
     }
@@ -428,6 +695,7 @@ impl CpuSetController {
 #[cfg(test)]
 mod tests {
     use crate::cpuset;
+    use crate::cpuset::CpuList;
     #[test]
     fn test_parse_range() {
         let test_cases = vec![
@@ -437,6 +705,8 @@ mod tests {
             "1-111".to_string(),
             "1,2,3,4".to_string(),
             "1-5,6-7,8-9".to_string(),
+            "0-3,8\n".to_string(),
+            " 0 - 1 , 4 ".to_string(),
         ];
         let expecteds = vec![
             vec![(1, 1), (2, 2), (4, 6), (9, 9)],
@@ -445,6 +715,8 @@ mod tests {
             vec![(1, 111)],
             vec![(1, 1), (2, 2), (3, 3), (4, 4)],
             vec![(1, 5), (6, 7), (8, 9)],
+            vec![(0, 3), (8, 8)],
+            vec![(0, 1), (4, 4)],
         ];
 
         for (i, case) in test_cases.into_iter().enumerate() {
@@ -454,4 +726,46 @@ mod tests {
             assert_eq!(range.unwrap(), expecteds[i]);
         }
     }
+
+    #[test]
+    fn test_cpu_list() {
+        let list: CpuList = "0-3,8,5,4\n".parse().unwrap();
+        assert_eq!(list.len(), 7);
+        assert!(list.contains(4));
+        assert!(!list.contains(6));
+        assert_eq!(list.ranges(), vec![(0, 5), (8, 8)]);
+        assert_eq!(list.to_string(), "0-5,8");
+        assert_eq!("".parse::<CpuList>().unwrap().to_string(), "");
+
+        assert!("3-1".parse::<CpuList>().is_err());
+        assert!("1,,2".parse::<CpuList>().is_err());
+        assert!("a".parse::<CpuList>().is_err());
+
+        assert_eq!("65535".parse::<CpuList>().unwrap().len(), 1);
+        assert!("65536".parse::<CpuList>().is_err());
+        assert!("0-4294967295".parse::<CpuList>().is_err());
+        assert!("0-18446744073709551615".parse::<CpuList>().is_err());
+    }
+
+    #[test]
+    fn test_cpu_list_set_operations() {
+        let a: CpuList = "0-3".parse().unwrap();
+        let b: CpuList = "2-5".parse().unwrap();
+        assert_eq!(a.union(&b).to_string(), "0-5");
+        assert_eq!(a.intersection(&b).to_string(), "2-3");
+        assert_eq!(a.difference(&b).to_string(), "0-1");
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert_eq!((&a).into_iter().sum::<u64>(), 6);
+    }
+
+    #[test]
+    fn test_cpu_list_cpu_set() {
+        let list: CpuList = "0,2,63-64".parse().unwrap();
+        let set = list.to_cpu_set().unwrap();
+        assert_eq!(CpuList::from_cpu_set(&set), list);
+
+        let too_large: CpuList = vec![libc::CPU_SETSIZE as u64].into_iter().collect();
+        assert!(too_large.to_cpu_set().is_err());
+    }
 }
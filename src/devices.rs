@@ -27,6 +27,7 @@ pub struct DevicesController {
 
 /// An enum holding the different types of devices that can be manipulated using this controller.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeviceType {
     /// The rule applies to all devices.
     All,
@@ -65,6 +66,7 @@ impl DeviceType {
 
 /// An enum with the permissions that can be allowed/denied to the control group.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DevicePermissions {
     /// Permission to read from the device.
     Read,
@@ -154,9 +156,9 @@ impl ControllerInternal for DevicesController {
         if res.update_values {
             for i in &res.devices {
                 if i.allow {
-                    let _ = self.allow_device(i.devtype, i.major, i.minor, &i.access);
+                    self.allow_device(i.devtype, i.major, i.minor, &i.access)?;
                 } else {
-                    let _ = self.deny_device(i.devtype, i.major, i.minor, &i.access);
+                    self.deny_device(i.devtype, i.major, i.minor, &i.access)?;
                 }
             }
         }
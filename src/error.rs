@@ -27,6 +27,24 @@ pub enum ErrorKind {
     /// This crate checks against this and operations will fail with this error.
     InvalidPath,
 
+    /// The resources that were about to be applied to the control group are invalid.
+    ///
+    /// The attached message describes which value was rejected and why. See
+    /// `Resources::validate()`.
+    InvalidResources(String),
+
+    /// The control group file needed for the operation does not exist.
+    ///
+    /// This is usually because the kernel is too old or was built without the required option,
+    /// e.g. `CONFIG_UCLAMP_TASK_GROUP` for the utilization clamps, or because the file only
+    /// exists in the other hierarchy, e.g. `cpu.weight` in V1.
+    ///
+    /// This applies to every controller: whenever the control group exists but one of its files
+    /// does not, reading or writing that file fails with this error rather than `ReadFailed` or
+    /// `WriteFailed`. Those are left for files that exist but cannot be accessed, and for control
+    /// groups that do not exist at all.
+    Unsupported,
+
     /// An unknown error has occured.
     Other,
 }
@@ -45,6 +63,10 @@ impl fmt::Display for Error {
             ErrorKind::ParseError => "unable to parse control group file",
             ErrorKind::InvalidOperation => "the requested operation is invalid",
             ErrorKind::InvalidPath => "the given path is invalid",
+            ErrorKind::InvalidResources(ref msg) => {
+                return write!(f, "the given resources are invalid: {}", msg)
+            }
+            ErrorKind::Unsupported => "the operation is not supported by the kernel",
             ErrorKind::Other => "an unknown error",
         };
 
@@ -0,0 +1,246 @@
+//! This module contains the `Notifier`, a handle through which the kernel informs the user about
+//! events occuring in a control group, e.g. an out-of-memory situation.
+//!
+//! In the V1 hierarchy, events are delivered through an `eventfd(2)` that is registered against a
+//! control file via `cgroup.event_control`. See the Kernel's documentation for more information,
+//! found at:
+//!  [Documentation/cgroup-v1/memory.txt](https://www.kernel.org/doc/Documentation/cgroup-v1/memory.txt)
+//!
+//! In the unified hierarchy, the kernel instead modifies a file like `memory.events`, which can be
+//! watched with `inotify(7)`.
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+use crate::error::*;
+use crate::error::ErrorKind::*;
+
+#[derive(Debug)]
+enum Source {
+    /// An eventfd registered via `cgroup.event_control`. The file it is registered against has to
+    /// stay open for as long as the registration should last.
+    EventFd { eventfd: File, _target: File },
+    /// An inotify instance watching a flat-keyed file for changes of the counter named `key`.
+    Inotify {
+        inotify: File,
+        path: PathBuf,
+        key: String,
+        last: u64,
+    },
+}
+
+/// A handle through which notifications about a control group are received.
+///
+/// The notifier can be waited upon with `wait()`, or the file descriptor returned by
+/// `as_raw_fd()` can be added to an event loop, followed by a call to `try_wait()` whenever it
+/// becomes readable. In the unified hierarchy, the file descriptor also becomes readable when other
+/// counters of the watched file change, so `try_wait()` may return `0` after all.
+///
+/// Dropping the notifier closes the file descriptors, which unregisters it from the kernel.
+#[derive(Debug)]
+pub struct Notifier {
+    source: Source,
+}
+
+fn os_error() -> Error {
+    Error::with_cause(Other, io::Error::last_os_error())
+}
+
+/// Returns true if `file` can be read from without blocking.
+fn readable(file: &File) -> Result<bool> {
+    let mut pfd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    match unsafe { libc::poll(&mut pfd, 1, 0) } {
+        n if n < 0 => Err(os_error()),
+        n => Ok(n > 0),
+    }
+}
+
+/// Read the counter `key` from a flat-keyed file like `memory.events`.
+fn read_counter(path: &Path, key: &str) -> Result<u64> {
+    let mut string = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut string))
+        .map_err(|e| Error::with_cause(ReadFailed, e))?;
+
+    for line in string.lines() {
+        let mut spl = line.split_whitespace();
+        if spl.next() == Some(key) {
+            return match spl.next() {
+                Some(v) => v.parse().map_err(|e| Error::with_cause(ParseError, e)),
+                None => Err(Error::new(ParseError)),
+            };
+        }
+    }
+
+    Err(Error::new(ParseError))
+}
+
+impl Notifier {
+    /// Registers a new eventfd against the control file `target` by writing
+    /// `"<eventfd> <target> <args>"` to `event_control`, which is the `cgroup.event_control` file of
+    /// the same control group.
+    pub(crate) fn register(target: File, mut event_control: File, args: &str) -> Result<Notifier> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(os_error());
+        }
+        let eventfd = unsafe { File::from_raw_fd(fd) };
+
+        let mut line = format!("{} {}", eventfd.as_raw_fd(), target.as_raw_fd());
+        if !args.is_empty() {
+            line.push(' ');
+            line.push_str(args);
+        }
+        event_control
+            .write_all(line.as_bytes())
+            .map_err(|e| Error::with_cause(WriteFailed, e))?;
+
+        Ok(Notifier {
+            source: Source::EventFd {
+                eventfd,
+                _target: target,
+            },
+        })
+    }
+
+    /// Watches the flat-keyed file at `path` (e.g. `memory.events`) and notifies whenever the
+    /// counter named `key` increases.
+    pub(crate) fn watch(path: PathBuf, key: &str) -> Result<Notifier> {
+        let last = read_counter(&path, key)?;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(os_error());
+        }
+        let inotify = unsafe { File::from_raw_fd(fd) };
+
+        let cpath =
+            CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::new(InvalidPath))?;
+        if unsafe { libc::inotify_add_watch(fd, cpath.as_ptr(), libc::IN_MODIFY) } < 0 {
+            return Err(os_error());
+        }
+
+        Ok(Notifier {
+            source: Source::Inotify {
+                inotify,
+                path,
+                key: key.to_string(),
+                last,
+            },
+        })
+    }
+
+    /// Blocks until at least one event has occured, and returns how many events occured since the
+    /// last call.
+    ///
+    /// In the unified hierarchy, this keeps blocking while only other counters of the watched file
+    /// change, so use `try_wait()` instead when the file descriptor is polled by an event loop.
+    pub fn wait(&mut self) -> Result<u64> {
+        match self.source {
+            Source::EventFd { ref mut eventfd, .. } => {
+                let mut buf = [0u8; 8];
+                eventfd
+                    .read_exact(&mut buf)
+                    .map_err(|e| Error::with_cause(ReadFailed, e))?;
+                Ok(u64::from_ne_bytes(buf))
+            }
+            Source::Inotify {
+                ref mut inotify,
+                ref path,
+                ref key,
+                ref mut last,
+            } => loop {
+                // the file is also modified when other counters change, so check ours
+                let mut buf = [0u8; 4096];
+                inotify
+                    .read(&mut buf)
+                    .map_err(|e| Error::with_cause(ReadFailed, e))?;
+                let now = read_counter(path, key)?;
+                if now > *last {
+                    let count = now - *last;
+                    *last = now;
+                    return Ok(count);
+                }
+            },
+        }
+    }
+
+    /// Returns how many events occured since the last call, without blocking. Returns `0` if no
+    /// event occured, e.g. when the file descriptor became readable because another counter of the
+    /// watched file changed.
+    pub fn try_wait(&mut self) -> Result<u64> {
+        match self.source {
+            Source::EventFd { ref mut eventfd, .. } => {
+                if !readable(eventfd)? {
+                    return Ok(0);
+                }
+                let mut buf = [0u8; 8];
+                eventfd
+                    .read_exact(&mut buf)
+                    .map_err(|e| Error::with_cause(ReadFailed, e))?;
+                Ok(u64::from_ne_bytes(buf))
+            }
+            Source::Inotify {
+                ref mut inotify,
+                ref path,
+                ref key,
+                ref mut last,
+            } => {
+                if readable(inotify)? {
+                    let mut buf = [0u8; 4096];
+                    inotify
+                        .read(&mut buf)
+                        .map_err(|e| Error::with_cause(ReadFailed, e))?;
+                }
+                let now = read_counter(path, key)?;
+                let count = now.saturating_sub(*last);
+                *last = (*last).max(now);
+                Ok(count)
+            }
+        }
+    }
+}
+
+impl AsRawFd for Notifier {
+    fn as_raw_fd(&self) -> RawFd {
+        match self.source {
+            Source::EventFd { ref eventfd, .. } => eventfd.as_raw_fd(),
+            Source::Inotify { ref inotify, .. } => inotify.as_raw_fd(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::events::{readable, Notifier, Source};
+
+    #[test]
+    fn test_try_wait_inotify() {
+        let path = std::env::temp_dir().join(format!("cgroups-rs-events-{}", std::process::id()));
+        fs::write(&path, "low 0\noom 0\n").unwrap();
+        let mut notifier = Notifier::watch(path.clone(), "oom").unwrap();
+        assert_eq!(notifier.try_wait().unwrap(), 0);
+
+        // another counter changing makes the fd readable, but is no event
+        fs::write(&path, "low 1\noom 0\n").unwrap();
+        assert_eq!(notifier.try_wait().unwrap(), 0);
+
+        fs::write(&path, "low 1\noom 2\n").unwrap();
+        assert_eq!(notifier.try_wait().unwrap(), 2);
+        assert_eq!(notifier.try_wait().unwrap(), 0);
+        match notifier.source {
+            Source::Inotify { ref inotify, .. } => assert!(!readable(inotify).unwrap()),
+            _ => unreachable!(),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -8,7 +8,9 @@ use std::path::PathBuf;
 use crate::error::*;
 use crate::error::ErrorKind::*;
 
-use crate::{ControllIdentifier, ControllerInternal, Controllers, Resources, Subsystem};
+use crate::{
+    ControllIdentifier, ControllerInternal, Controllers, FreezerResources, Resources, Subsystem,
+};
 
 /// A controller that allows controlling the `freezer` subsystem of a Cgroup.
 ///
@@ -25,6 +27,8 @@ pub struct FreezerController {
 }
 
 /// The current state of the control group
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FreezerState {
     /// The processes in the control group are _not_ frozen.
     Thawed,
@@ -48,7 +52,22 @@ impl ControllerInternal for FreezerController {
         &self.base
     }
 
-    fn apply(&self, _res: &Resources) -> Result<()> {
+    fn apply(&self, res: &Resources) -> Result<()> {
+        // get the resources that apply to this controller
+        let res: &FreezerResources = &res.freezer;
+
+        if res.update_values {
+            match res.state {
+                Some(FreezerState::Thawed) => {
+                    self.thaw()?;
+                }
+                Some(FreezerState::Frozen) | Some(FreezerState::Freezing) => {
+                    self.freeze()?;
+                }
+                None => (),
+            }
+        }
+
         Ok(())
     }
 }
@@ -106,7 +125,7 @@ impl FreezerController {
             let mut s = String::new();
             let res = file.read_to_string(&mut s);
             match res {
-                Ok(_) => match s.as_ref() {
+                Ok(_) => match s.trim() {
                     "FROZEN" => Ok(FreezerState::Frozen),
                     "THAWED" => Ok(FreezerState::Thawed),
                     "FREEZING" => Ok(FreezerState::Freezing),
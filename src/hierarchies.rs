@@ -1,10 +1,10 @@
 //! This module represents the various control group hierarchies the Linux kernel supports.
 //!
-//! Currently, we only support the cgroupv1 hierarchy, but in the future we will add support for
-//! the Unified Hierarchy.
+//! The cgroupv1 hierarchy is fully supported. The Unified Hierarchy (cgroupv2) is supported for
+//! the controllers that have been ported to it so far.
 
 use std::fs::File;
-use std::io::BufRead;
+use std::io::{BufRead, Read};
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 
@@ -110,6 +110,105 @@ impl V1 {
     }
 }
 
+/// The Unified Hierarchy, often referred to as "cgroupv2".
+///
+/// In this hierarchy, all controllers share the same directory, and which controllers are
+/// available in a control group is determined by the `cgroup.subtree_control` file of its parent.
+/// Right now, the `pids`, `memory`, `cpuset` and `cpu` controllers are supported.
+pub struct V2 {
+    mount_point: String,
+}
+
+impl Hierarchy for V2 {
+    fn subsystems(&self) -> Vec<Subsystem> {
+        let mut subs = vec![];
+        if self.check_support(Controllers::Pids) {
+            subs.push(Subsystem::Pid(PidController::new_v2(self.root())));
+        }
+        if self.check_support(Controllers::Mem) {
+            subs.push(Subsystem::Mem(MemController::new_v2(self.root())));
+        }
+        if self.check_support(Controllers::CpuSet) {
+            subs.push(Subsystem::CpuSet(CpuSetController::new_v2(self.root())));
+        }
+        if self.check_support(Controllers::Cpu) {
+            subs.push(Subsystem::Cpu(CpuController::new_v2(self.root())));
+        }
+
+        subs
+    }
+
+    fn root_control_group(&self) -> Cgroup<'_> {
+        Cgroup::load(self, "")
+    }
+
+    fn v2(&self) -> bool {
+        true
+    }
+
+    fn check_support(&self, sub: Controllers) -> bool {
+        let mut controllers = String::new();
+        let _ = File::open(self.root().join("cgroup.controllers"))
+            .and_then(|mut file| file.read_to_string(&mut controllers));
+        controllers
+            .split_whitespace()
+            .any(|c| c == sub.to_string())
+    }
+
+    fn root(&self) -> PathBuf {
+        PathBuf::from(self.mount_point.clone())
+    }
+}
+
+impl V2 {
+    /// Finds where the unified hierarchy is mounted to and returns a hierarchy in which control
+    /// groups can be created.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the unified hierarchy is not mounted, see `try_new()` for an alternative that
+    /// does not.
+    pub fn new() -> Self {
+        V2::try_new().expect("the unified hierarchy (cgroup2) is not mounted")
+    }
+
+    /// Same as `new()`, but returns `None` if the unified hierarchy is not mounted.
+    pub fn try_new() -> Option<Self> {
+        find_v2_mount().map(|mount_point| V2 { mount_point })
+    }
+}
+
+impl Default for V2 {
+    fn default() -> Self {
+        V2::new()
+    }
+}
+
+fn find_v2_mount() -> Option<String> {
+    let mountinfo_file = File::open("/proc/self/mountinfo").ok()?;
+    find_v2_mount_in(BufReader::new(&mountinfo_file))
+}
+
+/// Finds the mount point of the first `cgroup2` file system in `mountinfo`, which has the format
+/// of `/proc/self/mountinfo`.
+fn find_v2_mount_in<R: BufRead>(mountinfo: R) -> Option<String> {
+    for line in mountinfo.lines() {
+        let line = line.ok()?;
+        let index = match line.find(" - ") {
+            Some(index) => index,
+            None => continue,
+        };
+        let fstype = line[index + 3..].split_whitespace().next();
+        if fstype == Some("cgroup2") {
+            let cgroups_mount = line.split_whitespace().nth(4)?;
+            info!("found cgroup2 at {:?}", cgroups_mount);
+            return Some(cgroups_mount.to_string());
+        }
+    }
+
+    None
+}
+
 fn find_v1_mount() -> Option<String> {
     // Open mountinfo so we can get a parseable mount list
     let mountinfo_path = Path::new("/proc/self/mountinfo");
@@ -136,3 +235,250 @@ fn find_v1_mount() -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::cgroup::Cgroup;
+    use crate::cpu::{shares_to_weight, CpuController, CpuQuota};
+    use crate::cpuset::CpuSetController;
+    use crate::error::ErrorKind;
+    use crate::hierarchies::{find_v2_mount_in, V2};
+    use crate::memory::MemController;
+    use crate::{CgroupPid, Controllers, CpuResources, Hierarchy};
+    use crate::{MemoryResources, Resources};
+
+    static MOUNTINFO: &str = "\
+22 28 0:21 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw
+23 28 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:13 - proc proc rw
+25 22 0:24 / /sys/fs/cgroup ro,nosuid,nodev,noexec shared:9 - tmpfs tmpfs ro,mode=755
+26 25 0:25 / /sys/fs/cgroup/unified rw,nosuid,nodev,noexec,relatime shared:10 - cgroup2 cgroup2 rw
+27 25 0:26 / /sys/fs/cgroup/memory rw,nosuid,nodev,noexec,relatime shared:11 - cgroup cgroup rw,memory
+";
+
+    /// Builds a fake root of the unified hierarchy in which `controllers` are available.
+    fn fake_v2(name: &str, controllers: &str) -> V2 {
+        let root = std::env::temp_dir()
+            .join(format!("cgroups-rs-v2-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("cgroup.controllers"), controllers).unwrap();
+        V2 {
+            mount_point: root.to_str().unwrap().to_string(),
+        }
+    }
+
+    /// Creates the interface files that the kernel would create along with the control group.
+    fn touch(dir: &Path, files: &[&str]) {
+        for file in files {
+            fs::write(dir.join(file), "").unwrap();
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_find_v2_mount() {
+        assert_eq!(
+            find_v2_mount_in(MOUNTINFO.as_bytes()),
+            Some("/sys/fs/cgroup/unified".to_string())
+        );
+        let v1_only = MOUNTINFO.lines().filter(|l| !l.contains("cgroup2")).collect::<Vec<_>>();
+        assert_eq!(find_v2_mount_in(v1_only.join("\n").as_bytes()), None);
+        assert_eq!(find_v2_mount_in("garbage\n".as_bytes()), None);
+    }
+
+    #[test]
+    fn test_v2_subsystems() {
+        let hier = fake_v2("subsystems", "cpuset cpu io memory hugetlb pids rdma\n");
+        assert!(hier.check_support(Controllers::Mem));
+        assert!(hier.check_support(Controllers::Cpu));
+        assert!(!hier.check_support(Controllers::CpuAcct));
+        assert!(!hier.check_support(Controllers::Devices));
+
+        let subsystems = hier.subsystems();
+        let types = subsystems
+            .iter()
+            .map(|sub| sub.to_controller().control_type())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![Controllers::Pids, Controllers::Mem, Controllers::CpuSet, Controllers::Cpu]
+        );
+        // all controllers share the root directory
+        assert!(subsystems.iter().all(|sub| sub.to_controller().path() == hier.root()));
+        fs::remove_dir_all(hier.root()).unwrap();
+    }
+
+    #[test]
+    fn test_v2_create_and_add_task() {
+        let hier = fake_v2("create", "memory\n");
+        let root = hier.root();
+        let cg = Cgroup::load(&hier, "parent/child");
+        cg.create().unwrap();
+
+        // the controller is enabled in every ancestor, but not in the new control group
+        assert_eq!(read(&root.join("cgroup.subtree_control")), "+memory");
+        assert_eq!(read(&root.join("parent/cgroup.subtree_control")), "+memory");
+        let dir = root.join("parent/child");
+        assert!(dir.is_dir());
+        assert!(!dir.join("cgroup.subtree_control").exists());
+
+        touch(&dir, &["cgroup.procs"]);
+        cg.add_task(CgroupPid::from(1234)).unwrap();
+        assert_eq!(read(&dir.join("cgroup.procs")), "1234");
+        assert!(!dir.join("tasks").exists());
+        assert_eq!(cg.tasks(), vec![CgroupPid::from(1234)]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_v2_memory_apply() {
+        let hier = fake_v2("memory", "memory\n");
+        let cg = Cgroup::load(&hier, "memory");
+        cg.create().unwrap();
+        let dir = hier.root().join("memory");
+        touch(&dir, &["memory.max", "memory.low", "memory.high", "memory.swap.max"]);
+
+        let mut res = Resources {
+            memory: MemoryResources {
+                update_values: true,
+                memory_hard_limit: Some(1024 * 1024 * 1024),
+                memory_swap_limit: Some(1536 * 1024 * 1024),
+                memory_soft_limit: Some(256 * 1024 * 1024),
+                memory_high: Some(-1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        cg.apply(&res).unwrap();
+        let mem: &MemController = cg.controller_of().unwrap();
+        assert_eq!(mem.max().unwrap(), 1024 * 1024 * 1024);
+        // memory.swap.max only limits the swap, not memory and swap together
+        assert_eq!(mem.swap_max().unwrap(), 512 * 1024 * 1024);
+        assert_eq!(mem.low().unwrap(), 256 * 1024 * 1024);
+        assert_eq!(read(&dir.join("memory.high")), "max");
+        assert_eq!(mem.high().unwrap(), -1);
+
+        res.memory.memory_hard_limit = None;
+        match cg.apply(&res) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("a memory+swap limit without a memory limit should be rejected"),
+        }
+        assert!(res.validate(&hier).is_err());
+        fs::remove_dir_all(hier.root()).unwrap();
+    }
+
+    #[test]
+    fn test_v2_cpu_apply() {
+        let hier = fake_v2("cpu", "cpu\n");
+        let cg = Cgroup::load(&hier, "cpu");
+        cg.create().unwrap();
+        let dir = hier.root().join("cpu");
+        touch(&dir, &["cpu.weight"]);
+        fs::write(dir.join("cpu.max"), "max 100000\n").unwrap();
+
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                shares: Some(1024),
+                quota: Some(CpuQuota::Micros(50_000)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        cg.apply(&res).unwrap();
+        let cpu: &CpuController = cg.controller_of().unwrap();
+        assert_eq!(cpu.weight().unwrap(), shares_to_weight(1024));
+        // the period that is not given is kept
+        assert_eq!(cpu.max().unwrap(), (CpuQuota::Micros(50_000), 100_000));
+
+        res.cpu.shares = None;
+        res.cpu.quota = None;
+        res.cpu.period = Some(200_000);
+        cg.apply(&res).unwrap();
+        assert_eq!(read(&dir.join("cpu.max")), "50000 200000");
+        fs::remove_dir_all(hier.root()).unwrap();
+    }
+
+    #[test]
+    fn test_v2_cpuset() {
+        let hier = fake_v2("cpuset", "cpuset\n");
+        let root = hier.root();
+        fs::write(root.join("cpuset.cpus.effective"), "0-3\n").unwrap();
+        fs::write(root.join("cpuset.mems.effective"), "0\n").unwrap();
+        let root_cg = hier.root_control_group();
+        let cpuset: &CpuSetController = root_cg.controller_of().unwrap();
+        assert_eq!(cpuset.cpuset().effective_cpus, "0-3".parse().unwrap());
+        assert_eq!(cpuset.cpuset().effective_mems, "0".parse().unwrap());
+
+        let cg = Cgroup::load(&hier, "cpuset");
+        cg.create().unwrap();
+        let dir = root.join("cpuset");
+        touch(&dir, &["cpuset.cpus", "cpuset.mems"]);
+
+        // the exclusive flag has no file in the unified hierarchy and is skipped
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                cpus: Some("1-2".parse().unwrap()),
+                mems: Some("0".parse().unwrap()),
+                cpu_exclusive: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        res.validate(&hier).unwrap();
+        cg.apply(&res).unwrap();
+        assert_eq!(read(&dir.join("cpuset.cpus")), "1-2");
+        assert_eq!(read(&dir.join("cpuset.mems")), "0");
+        assert!(!dir.join("cpuset.cpu_exclusive").exists());
+
+        // the host's CPUs are read from cpuset.cpus.effective
+        res.cpu.cpus = Some("4".parse().unwrap());
+        assert!(res.validate(&hier).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_v2_unified() {
+        let hier = fake_v2("unified", "memory\n");
+        let cg = Cgroup::load(&hier, "unified");
+        cg.create().unwrap();
+        let dir = hier.root().join("unified");
+        touch(&dir, &["memory.high", "memory.oom.group"]);
+
+        let mut res = Resources::default();
+        res.unified.insert("memory.high".to_string(), "1073741824".to_string());
+        res.unified.insert("memory.oom.group".to_string(), "1".to_string());
+        res.validate(&hier).unwrap();
+        cg.apply(&res).unwrap();
+        assert_eq!(read(&dir.join("memory.high")), "1073741824");
+        assert_eq!(read(&dir.join("memory.oom.group")), "1");
+
+        res.unified.clear();
+        res.unified.insert("memory.zswap.max".to_string(), "0".to_string());
+        assert_eq!(cg.apply(&res).unwrap_err().kind(), &ErrorKind::Unsupported);
+
+        // keys may only name files in the directory of the control group
+        res.unified.clear();
+        res.unified.insert("../memory.high".to_string(), "0".to_string());
+        assert!(res.validate(&hier).is_err());
+        match cg.apply(&res) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("a key outside of the control group should be rejected"),
+        }
+        assert!(!hier.root().join("memory.high").exists());
+        fs::remove_dir_all(hier.root()).unwrap();
+    }
+}
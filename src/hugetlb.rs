@@ -44,7 +44,7 @@ impl ControllerInternal for HugeTlbController {
 
         if res.update_values {
             for i in &res.limits {
-                let _ = self.set_limit_in_bytes(&i.size, i.limit);
+                self.set_limit_in_bytes(&i.size, i.limit)?;
                 if self.limit_in_bytes(&i.size)? != i.limit {
                     return Err(Error::new(Other));
                 }
@@ -94,9 +94,33 @@ impl HugeTlbController {
     }
 
     /// Whether the system supports `hugetlb_size` hugepages.
-    pub fn size_supported(&self, _hugetlb_size: &str) -> bool {
-        // TODO
-        true
+    pub fn size_supported(&self, hugetlb_size: &str) -> bool {
+        self.get_base()
+            .join(format!("hugetlb.{}.limit_in_bytes", hugetlb_size))
+            .exists()
+    }
+
+    /// The sizes of the hugepages supported by the system, i.e. `2MB`, `1GB`, etc.
+    pub fn sizes(&self) -> Vec<String> {
+        let mut sizes = self
+            .get_base()
+            .read_dir()
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .filter_map(|name| {
+                        if name.starts_with("hugetlb.") && name.ends_with(".limit_in_bytes") {
+                            Some(name["hugetlb.".len()..name.len() - ".limit_in_bytes".len()]
+                                .to_string())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        sizes.sort();
+        sizes
     }
 
     /// Check how many times has the limit of `hugetlb_size` hugepages been hit.
@@ -128,6 +152,26 @@ impl HugeTlbController {
         ).and_then(read_u64_from)
     }
 
+    /// Reset the maximum observed usage of memory that is backed by hugepages of a certain size
+    /// (`hugetlb_size`).
+    pub fn reset_max_usage(&self, hugetlb_size: &str) -> Result<()> {
+        self.open_path(
+            &format!("hugetlb.{}.max_usage_in_bytes", hugetlb_size),
+            true,
+        ).and_then(|mut file| {
+            file.write_all(b"0")
+                .map_err(|e| Error::with_cause(WriteFailed, e))
+        })
+    }
+
+    /// Reset the maximum observed usage of memory that is backed by hugepages, for all sizes of
+    /// hugepages.
+    pub fn reset_peaks(&self) -> Result<()> {
+        self.sizes()
+            .iter()
+            .try_for_each(|size| self.reset_max_usage(size))
+    }
+
     /// Set the limit (in bytes) of how much memory can be backed by hugepages of a certain size
     /// (`hugetlb_size`).
     pub fn set_limit_in_bytes(&self, hugetlb_size: &str, limit: u64) -> Result<()> {
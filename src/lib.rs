@@ -1,5 +1,4 @@
-use log::*;
-
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
@@ -11,15 +10,19 @@ pub mod cpuacct;
 pub mod cpuset;
 pub mod devices;
 pub mod error;
+pub mod events;
 pub mod freezer;
 pub mod hierarchies;
 pub mod hugetlb;
 pub mod memory;
 pub mod net_cls;
 pub mod net_prio;
+#[cfg(feature = "oci")]
+pub mod oci;
 pub mod perf_event;
 pub mod pid;
 pub mod rdma;
+pub mod topology;
 pub mod cgroup_builder;
 
 use crate::blkio::BlkIoController;
@@ -120,6 +123,11 @@ mod sealed {
         fn get_path_mut(&mut self) -> &mut PathBuf;
         fn get_base(&self) -> &PathBuf;
 
+        /// Whether the controller is part of the unified hierarchy.
+        fn is_v2(&self) -> bool {
+            false
+        }
+
         fn verify_path(&self) -> Result<()> {
             if self.get_path().starts_with(self.get_base()) {
                 Ok(())
@@ -134,6 +142,11 @@ mod sealed {
 
             self.verify_path()?;
 
+            // control group files can never be created, so a missing one is not supported
+            if !path.exists() && self.get_path().exists() {
+                return Err(Error::new(ErrorKind::Unsupported));
+            }
+
             if w {
                 match File::create(&path) {
                     Err(e) => return Err(Error::with_cause(ErrorKind::WriteFailed, e)),
@@ -176,7 +189,9 @@ pub trait Controller {
     fn apply(&self, res: &Resources) -> Result<()>;
 
     /// Create this controller
-    fn create(&self);
+    ///
+    /// It is not an error if the controller already exists.
+    fn create(&self) -> Result<()>;
 
     /// Does this controller already exist?
     fn exists(&self) -> bool;
@@ -207,12 +222,34 @@ impl<T> Controller for T where T: ControllerInternal {
     }
 
     /// Create this controller
-    fn create(&self) {
+    fn create(&self) -> Result<()> {
         self.verify_path().expect("path should be valid");
 
+        if self.is_v2() {
+            // in the unified hierarchy, the controller has to be enabled in all the ancestors
+            let name = format!("+{}", ControllerInternal::control_type(self).to_string());
+            let mut ancestors = self
+                .get_path()
+                .ancestors()
+                .skip(1)
+                .take_while(|p| p.starts_with(self.get_base()))
+                .collect::<Vec<_>>();
+            ancestors.reverse();
+            for dir in ancestors {
+                match ::std::fs::create_dir(dir) {
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == ::std::io::ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(Error::with_cause(ErrorKind::WriteFailed, e)),
+                }
+                ::std::fs::write(dir.join("cgroup.subtree_control"), &name)
+                    .map_err(|e| Error::with_cause(ErrorKind::WriteFailed, e))?;
+            }
+        }
+
         match ::std::fs::create_dir(self.get_path()) {
-            Ok(_) => (),
-            Err(e) => warn!("error create_dir {:?}", e),
+            Ok(_) => Ok(()),
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::AlreadyExists => Ok(()),
+            Err(e) => Err(Error::with_cause(ErrorKind::WriteFailed, e)),
         }
     }
 
@@ -230,7 +267,8 @@ impl<T> Controller for T where T: ControllerInternal {
 
     /// Attach a task to this controller.
     fn add_task(&self, pid: &CgroupPid) -> Result<()> {
-        self.open_path("tasks", true).and_then(|mut file| {
+        let file = if self.is_v2() { "cgroup.procs" } else { "tasks" };
+        self.open_path(file, true).and_then(|mut file| {
             file.write_all(pid.pid.to_string().as_ref())
                 .map_err(|e| Error::with_cause(ErrorKind::WriteFailed, e))
         })
@@ -238,7 +276,8 @@ impl<T> Controller for T where T: ControllerInternal {
 
     /// Get the list of tasks that this controller has.
     fn tasks(&self) -> Vec<CgroupPid> {
-        self.open_path("tasks", false)
+        let file = if self.is_v2() { "cgroup.procs" } else { "tasks" };
+        self.open_path(file, false)
             .and_then(|file| {
                 let bf = BufReader::new(file);
                 let mut v = Vec::new();
@@ -258,8 +297,7 @@ pub trait ControllIdentifier {
     fn controller_type() -> Controllers;
 }
 
-/// Control group hierarchy (right now, only V1 is supported, but in the future Unified will be
-/// implemented as well).
+/// Control group hierarchy, either the original V1 or the unified (V2) hierarchy.
 pub trait Hierarchy {
     /// Returns what subsystems are supported by the hierarchy.
     fn subsystems(&self) -> Vec<Subsystem>;
@@ -270,6 +308,11 @@ pub trait Hierarchy {
     /// Return a handle to the root control group in the hierarchy.
     fn root_control_group(&self) -> Cgroup;
 
+    /// Whether this is the unified (V2) hierarchy.
+    fn v2(&self) -> bool {
+        false
+    }
+
     /// Checks whether a certain subsystem is supported in the hierarchy.
     ///
     /// This is an internal function and should not be used.
@@ -279,30 +322,66 @@ pub trait Hierarchy {
 
 /// Resource limits for the memory subsystem.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct MemoryResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
     /// How much memory (in bytes) can the kernel consume.
-    pub kernel_memory_limit: u64,
+    ///
+    /// For this and the other limits in this structure, `-1` removes the limit and `None` leaves
+    /// the current value untouched.
+    pub kernel_memory_limit: Option<i64>,
     /// Upper limit of memory usage of the control group's tasks.
-    pub memory_hard_limit: u64,
+    pub memory_hard_limit: Option<i64>,
     /// How much memory the tasks in the control group can use when the system is under memory
     /// pressure.
-    pub memory_soft_limit: u64,
+    pub memory_soft_limit: Option<i64>,
     /// How much of the kernel's memory (in bytes) can be used for TCP-related buffers.
-    pub kernel_tcp_memory_limit: u64,
+    pub kernel_tcp_memory_limit: Option<i64>,
     /// How much memory and swap together can the tasks in the control group use.
-    pub memory_swap_limit: u64,
+    ///
+    /// In the unified hierarchy, where swap is limited on its own, the swap limit is computed by
+    /// subtracting `memory_hard_limit` from this.
+    pub memory_swap_limit: Option<i64>,
     /// Controls the tendency of the kernel to swap out parts of the address space of the tasks to
     /// disk. Lower value implies less likely.
     ///
     /// Note, however, that a value of zero does not mean the process is never swapped out. Use the
     /// traditional `mlock(2)` system call for that purpose.
-    pub swappiness: u64,
+    pub swappiness: Option<u64>,
+    /// The amount of memory that is protected from reclaim. Only available in the unified
+    /// hierarchy.
+    pub memory_min: Option<i64>,
+    /// The amount of memory that is only reclaimed if there is no unprotected memory left to
+    /// reclaim. Only available in the unified hierarchy, where it defaults to `memory_soft_limit`.
+    pub memory_low: Option<i64>,
+    /// The memory usage above which the tasks are throttled. Only available in the unified
+    /// hierarchy.
+    pub memory_high: Option<i64>,
+    /// The swap usage above which the allocations of the tasks are throttled. Only available in
+    /// the unified hierarchy.
+    pub swap_high: Option<i64>,
+    /// How much memory can be used by zswap. Only available in the unified hierarchy.
+    pub zswap_max: Option<i64>,
+    /// If true, the OOM killer is disabled for the tasks in the control group. Tasks that would be
+    /// killed are instead paused until memory is freed.
+    pub oom_kill_disable: Option<bool>,
+    /// If true, the OOM killer kills all tasks of the control group together, instead of picking
+    /// a single one. Only available in the unified hierarchy.
+    pub oom_group: Option<bool>,
+    /// If true, memory usage of the descendant control groups is accounted to and reclaimed from
+    /// this control group too.
+    pub use_hierarchy: Option<bool>,
+    /// Controls whether (and which type of) charges are moved along with a task when it is moved
+    /// into the control group.
+    pub move_charge_at_immigrate: Option<memory::MoveCharge>,
 }
 
 /// Resources limits on the number of processes.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct PidResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
@@ -316,32 +395,62 @@ pub struct PidResources {
 
 /// Resources limits about how the tasks can use the CPU.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct CpuResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
     // cpuset
-    /// A comma-separated list of CPU IDs where the task in the control group can run. Dashes
-    /// between numbers indicate ranges.
-    pub cpus: String,
-    /// Same syntax as the `cpus` field of this structure, but applies to memory nodes instead of
-    /// processors.
-    pub mems: String,
+    /// The CPUs where the tasks in the control group can run.
+    pub cpus: Option<cpuset::CpuList>,
+    /// The memory nodes the tasks in the control group can use.
+    pub mems: Option<cpuset::CpuList>,
+    /// If true, no other control groups can share the CPUs listed in the `cpus` field.
+    pub cpu_exclusive: Option<bool>,
+    /// If true, no other control groups can share the memory nodes listed in the `mems` field.
+    pub mem_exclusive: Option<bool>,
+    /// If true, kernel allocations are restricted to the memory nodes listed in the `mems` field.
+    pub mem_hardwall: Option<bool>,
+    /// If true, memory used by the tasks is migrated to the new nodes whenever `mems` changes.
+    pub memory_migrate: Option<bool>,
+    /// If true, filesystem buffers are spread evenly across the nodes listed in `mems`.
+    pub memory_spread_page: Option<bool>,
+    /// If true, kernel slab caches for file I/O are spread evenly across the nodes listed in
+    /// `mems`.
+    pub memory_spread_slab: Option<bool>,
+    /// If true, the kernel will attempt to rebalance the load between the CPUs listed in `cpus`.
+    pub sched_load_balance: Option<bool>,
+    /// How much work the kernel should do to rebalance the load, see `CpuSet` for the values.
+    pub sched_relax_domain_level: Option<i64>,
     // cpu
     /// Weight of how much of the total CPU time should this control group get. Note that this is
     /// hierarchical, so this is weighted against the siblings of this control group.
-    pub shares: u64,
-    /// In one `period`, how much can the tasks run in nanoseconds.
-    pub quota: i64,
-    /// Period of time in nanoseconds.
-    pub period: u64,
-    /// This is currently a no-operation.
-    pub realtime_runtime: i64,
-    /// This is currently a no-operation.
-    pub realtime_period: u64,
+    pub shares: Option<u64>,
+    /// In one `period`, how long can the tasks run.
+    pub quota: Option<cpu::CpuQuota>,
+    /// Period of time in microseconds.
+    pub period: Option<u64>,
+    /// In one `realtime_period`, how long can the realtime tasks run in microseconds. `-1`
+    /// removes the restriction. This is taken out of the parent's realtime budget.
+    pub realtime_runtime: Option<i64>,
+    /// Period of time in microseconds after which the realtime runtime is replenished.
+    pub realtime_period: Option<u64>,
+    /// How long in microseconds can the tasks run above the `quota` in one `period`, using up the
+    /// time they left unused in earlier periods.
+    pub burst: Option<u64>,
+    /// The minimum utilization the scheduler assumes for the tasks, in hundredths of a percent,
+    /// i.e. from `0` to `10000`. Unlike `CpuController::set_uclamp_min()`, which takes a
+    /// percentage, this is an integer so that `Resources` can be compared exactly.
+    pub uclamp_min: Option<u64>,
+    /// The maximum utilization the scheduler assumes for the tasks, in hundredths of a percent,
+    /// i.e. from `0` to `10000`, see `uclamp_min`.
+    pub uclamp_max: Option<u64>,
 }
 
 /// A device resource that can be allowed or denied access to.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct DeviceResource {
     /// If true, access to the device is allowed, otherwise it's denied.
     pub allow: bool,
@@ -357,6 +466,8 @@ pub struct DeviceResource {
 
 /// Limit the usage of devices for the control group's tasks.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct DeviceResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
@@ -366,6 +477,8 @@ pub struct DeviceResources {
 
 /// Assigned priority for a network device.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct NetworkPriority {
     /// The name (as visible in `ifconfig`) of the interface.
     pub name: String,
@@ -376,19 +489,23 @@ pub struct NetworkPriority {
 /// Collections of limits and tags that can be imposed on packets emitted by the tasks in the
 /// control group.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct NetworkResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
     /// The networking class identifier to attach to the packets.
     ///
     /// This can then later be used in iptables and such to have special rules.
-    pub class_id: u64,
+    pub class_id: Option<u64>,
     /// Priority of the egress traffic for each interface.
     pub priorities: Vec<NetworkPriority>,
 }
 
 /// A hugepage type and its consumption limit for the control group.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct HugePageResource {
     /// The size of the hugepage, i.e. `2MB`, `1GB`, etc.
     pub size: String,
@@ -399,6 +516,8 @@ pub struct HugePageResource {
 
 /// Provides the ability to set consumption limit on each type of hugepages.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct HugePageResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
@@ -408,19 +527,23 @@ pub struct HugePageResources {
 
 /// Weight for a particular block device.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct BlkIoDeviceResource {
     /// The major number of the device.
     pub major: u64,
     /// The minor number of the device.
     pub minor: u64,
     /// The weight of the device against the descendant nodes.
-    pub weight: u16,
+    pub weight: Option<u16>,
     /// The weight of the device against the sibling nodes.
-    pub leaf_weight: u16,
+    pub leaf_weight: Option<u16>,
 }
 
 /// Provides the ability to throttle a device (both byte/sec, and IO op/s)
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct BlkIoDeviceThrottleResource {
     /// The major number of the device.
     pub major: u64,
@@ -432,13 +555,15 @@ pub struct BlkIoDeviceThrottleResource {
 
 /// General block I/O resource limits.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct BlkIoResources {
     /// Whether values should be applied to the controller.
     pub update_values: bool,
     /// The weight of the control group against descendant nodes.
-    pub weight: u16,
+    pub weight: Option<u16>,
     /// The weight of the control group against sibling nodes.
-    pub leaf_weight: u16,
+    pub leaf_weight: Option<u16>,
     /// For each device, a separate weight (both normal and leaf) can be provided.
     pub weight_device: Vec<BlkIoDeviceResource>,
     /// Throttled read bytes/second can be provided for each device.
@@ -451,8 +576,45 @@ pub struct BlkIoResources {
     pub throttle_write_iops_device: Vec<BlkIoDeviceThrottleResource>,
 }
 
+/// The maximum usage of RDMA/IB resources on a particular device.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RdmaResource {
+    /// The name of the device, i.e. `mlx4_0`.
+    pub device: String,
+    /// The maximum number of HCA handles that can be opened.
+    pub hca_handles: Option<u32>,
+    /// The maximum number of HCA objects that can be created.
+    pub hca_objects: Option<u32>,
+}
+
+/// Provides the ability to limit the usage of RDMA/IB resources on each device.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RdmaResources {
+    /// Whether values should be applied to the controller.
+    pub update_values: bool,
+    /// Set a limit for each RDMA/IB device.
+    pub limits: Vec<RdmaResource>,
+}
+
+/// The state the freezer should put the tasks of the control group in.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FreezerResources {
+    /// Whether values should be applied to the controller.
+    pub update_values: bool,
+    /// `Frozen` freezes the tasks, `Thawed` thaws them.
+    pub state: Option<freezer::FreezerState>,
+}
+
 /// The resource limits and constraints that will be set on the control group.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Resources {
     /// Memory usage related limits.
     pub memory: MemoryResources,
@@ -468,11 +630,287 @@ pub struct Resources {
     pub hugepages: HugePageResources,
     /// Block device I/O related limits.
     pub blkio: BlkIoResources,
+    /// RDMA/IB related limits.
+    pub rdma: RdmaResources,
+    /// Freezer state.
+    pub freezer: FreezerResources,
+    /// Raw key-value pairs for the interface files of the unified (cgroup v2) hierarchy, e.g.
+    /// `"memory.high" => "1G"`.
+    ///
+    /// `Cgroup::apply()` writes each value to the file named by its key in the directory of the
+    /// control group, after the controllers have applied the other resources. They are rejected
+    /// with `InvalidResources` in the V1 hierarchy.
+    pub unified: HashMap<String, String>,
+}
+
+fn invalid(msg: String) -> Result<()> {
+    Err(Error::new(ErrorKind::InvalidResources(msg)))
+}
+
+/// Checks that `key` of `Resources::unified` names a file in the directory of the control group.
+fn check_unified_key(key: &str) -> Result<()> {
+    if key.is_empty() || key == "." || key == ".." || key.contains('/') {
+        return invalid(format!("invalid unified resource {:?}", key));
+    }
+    Ok(())
+}
+
+/// Checks that every ID in `wanted` is part of `available`.
+fn check_id_list(what: &str, wanted: &cpuset::CpuList, available: &cpuset::CpuList) -> Result<()> {
+    // an empty list means the host's list could not be read, so skip the check
+    if available.is_empty() {
+        return Ok(());
+    }
+    let missing = wanted.difference(available);
+    match missing.len() {
+        0 => Ok(()),
+        1 => invalid(format!("{} {} is not available on this system", what, missing)),
+        _ => invalid(format!("{}s {} are not available on this system", what, missing)),
+    }
+}
+
+impl MemoryResources {
+    fn validate(&self, v2: bool) -> Result<()> {
+        let limits = [
+            ("kernel memory limit", self.kernel_memory_limit),
+            ("memory hard limit", self.memory_hard_limit),
+            ("memory soft limit", self.memory_soft_limit),
+            ("kernel TCP memory limit", self.kernel_tcp_memory_limit),
+            ("memory+swap limit", self.memory_swap_limit),
+            ("memory.min", self.memory_min),
+            ("memory.low", self.memory_low),
+            ("memory.high", self.memory_high),
+            ("swap.high", self.swap_high),
+            ("zswap.max", self.zswap_max),
+        ];
+        for &(what, limit) in limits.iter() {
+            if let Some(l) = limit {
+                if l < -1 {
+                    return invalid(format!("{} must be -1 or non-negative, got {}", what, l));
+                }
+            }
+        }
+        if let Some(swappiness) = self.swappiness {
+            if swappiness > 100 {
+                return invalid(format!("swappiness must be at most 100, got {}", swappiness));
+            }
+        }
+        // the kernel rejects a memory+swap limit that is smaller than the memory limit
+        if let (Some(mem), Some(swap)) = (self.memory_hard_limit, self.memory_swap_limit) {
+            if swap != -1 && (mem == -1 || swap < mem) {
+                return invalid(format!(
+                    "memory+swap limit ({}) must not be smaller than the memory limit ({})",
+                    swap, mem
+                ));
+            }
+        }
+        // memory.swap.max is derived from the difference of the two limits
+        if let (true, Some(swap), None) = (v2, self.memory_swap_limit, self.memory_hard_limit) {
+            if swap != -1 {
+                return invalid(format!(
+                    "memory+swap limit ({}) requires a memory limit in the unified hierarchy",
+                    swap
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CpuResources {
+    fn validate(&self, hier: &dyn Hierarchy) -> Result<()> {
+        let root = hier.root_control_group();
+        let (host_cpus, host_mems) = match root.controller_of::<CpuSetController>() {
+            Some(c) => {
+                let cpuset = c.cpuset();
+                (cpuset.effective_cpus, cpuset.effective_mems)
+            }
+            None => (cpuset::CpuList::new(), cpuset::CpuList::new()),
+        };
+        if let Some(ref cpus) = self.cpus {
+            check_id_list("CPU", cpus, &host_cpus)?;
+        }
+        if let Some(ref mems) = self.mems {
+            check_id_list("memory node", mems, &host_mems)?;
+        }
+        if let Some(level) = self.sched_relax_domain_level {
+            if !(-1..=5).contains(&level) {
+                return invalid(format!(
+                    "sched_relax_domain_level must be between -1 and 5, got {}",
+                    level
+                ));
+            }
+        }
+        if let Some(shares) = self.shares {
+            if !(2..=262_144).contains(&shares) {
+                return invalid(format!(
+                    "CPU shares must be between 2 and 262144, got {}",
+                    shares
+                ));
+            }
+        }
+        if let Some(period) = self.period {
+            if !(1000..=1_000_000).contains(&period) {
+                return invalid(format!(
+                    "CFS period must be between 1000 and 1000000 microseconds, got {}",
+                    period
+                ));
+            }
+        }
+        if let Some(cpu::CpuQuota::Micros(quota)) = self.quota {
+            if quota < 1000 {
+                return invalid(format!(
+                    "CFS quota must be unlimited or at least 1000 microseconds, got {}",
+                    quota
+                ));
+            }
+        }
+        if let (Some(cpu::CpuQuota::Micros(quota)), Some(burst)) = (self.quota, self.burst) {
+            if burst > quota {
+                return invalid(format!(
+                    "CFS burst ({}) must not exceed the quota ({})",
+                    burst, quota
+                ));
+            }
+        }
+        if let Some(runtime) = self.realtime_runtime {
+            if runtime < -1 {
+                return invalid(format!(
+                    "realtime runtime must be -1 or non-negative, got {}",
+                    runtime
+                ));
+            }
+            if let Some(period) = self.realtime_period {
+                if runtime > period as i64 {
+                    return invalid(format!(
+                        "realtime runtime ({}) must not exceed the realtime period ({})",
+                        runtime, period
+                    ));
+                }
+            }
+        }
+        for &(what, uclamp) in [("minimum", self.uclamp_min), ("maximum", self.uclamp_max)].iter() {
+            if let Some(uclamp) = uclamp {
+                if uclamp > 10_000 {
+                    return invalid(format!(
+                        "the {} utilization clamp must be at most 10000 (100%), got {}",
+                        what, uclamp
+                    ));
+                }
+            }
+        }
+        if let (Some(min), Some(max)) = (self.uclamp_min, self.uclamp_max) {
+            if min > max {
+                return invalid(format!(
+                    "the minimum utilization clamp ({}) must not exceed the maximum ({})",
+                    min, max
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_blkio_weight(what: &str, weight: Option<u16>) -> Result<()> {
+    match weight {
+        Some(w) if !(10..=1000).contains(&w) => {
+            invalid(format!("{} must be between 10 and 1000, got {}", what, w))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl BlkIoResources {
+    fn validate(&self) -> Result<()> {
+        check_blkio_weight("block I/O weight", self.weight)?;
+        check_blkio_weight("block I/O leaf weight", self.leaf_weight)?;
+        for dev in &self.weight_device {
+            let what = format!("block I/O weight of device {}:{}", dev.major, dev.minor);
+            check_blkio_weight(&what, dev.weight)?;
+            check_blkio_weight(&format!("leaf {}", what), dev.leaf_weight)?;
+        }
+        Ok(())
+    }
+}
+
+impl HugePageResources {
+    fn validate(&self, hier: &dyn Hierarchy) -> Result<()> {
+        let root = hier.root_control_group();
+        // without the hugetlb controller these limits are never applied
+        let hugetlb = match root.controller_of::<HugeTlbController>() {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        for limit in &self.limits {
+            if !hugetlb.size_supported(&limit.size) {
+                return invalid(format!(
+                    "hugepage size \"{}\" is not supported by this system",
+                    limit.size
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Resources {
+    /// Checks that the resources can be applied to a control group in the hierarchy `hier`.
+    ///
+    /// This verifies that the values are in the ranges accepted by the kernel, that related
+    /// values are consistent with each other (e.g., the memory+swap limit is not smaller than
+    /// the memory limit) and that the CPUs, memory nodes and hugepage sizes that are referred to
+    /// exist on the host. Only the parts whose `update_values` is set are checked.
+    ///
+    /// Limits that depend on the parent of the control group, i.e. the realtime budget and the
+    /// CFS bandwidth, are checked by `Cgroup::apply()` before anything is written instead.
+    ///
+    /// Returns an error of kind `InvalidResources` describing the first problem found.
+    pub fn validate(&self, hier: &dyn Hierarchy) -> Result<()> {
+        if self.memory.update_values {
+            let v2 = hier
+                .root_control_group()
+                .controller_of::<MemController>()
+                .is_some_and(|c| c.is_v2());
+            self.memory.validate(v2)?;
+        }
+        if self.pid.update_values {
+            if let pid::PidMax::Value(v) = self.pid.maximum_number_of_processes {
+                if v < 0 {
+                    return invalid(format!(
+                        "maximum number of processes must be non-negative, got {}",
+                        v
+                    ));
+                }
+            }
+        }
+        if self.cpu.update_values {
+            self.cpu.validate(hier)?;
+        }
+        if self.hugepages.update_values {
+            self.hugepages.validate(hier)?;
+        }
+        if self.blkio.update_values {
+            self.blkio.validate()?;
+        }
+        self.validate_unified(hier.v2())
+    }
+
+    /// Checks the keys of `unified`, which can only be applied in the unified hierarchy.
+    pub(crate) fn validate_unified(&self, v2: bool) -> Result<()> {
+        if self.unified.is_empty() {
+            return Ok(());
+        }
+        if !v2 {
+            return invalid("unified resources require the unified hierarchy".to_string());
+        }
+        self.unified.keys().try_for_each(|key| check_unified_key(key))
+    }
 }
 
 /// A structure representing a `pid`. Currently implementations exist for `u64` and
 /// `std::process::Child`.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CgroupPid {
     /// The process identifier
     pub pid: u64,
@@ -579,3 +1017,89 @@ impl Subsystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use crate::{BlkIoDeviceResource, BlkIoResources, MemoryResources, Resources};
+
+    fn assert_invalid(res: crate::error::Result<()>) {
+        match res {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("resources should have been rejected"),
+        }
+    }
+
+    #[test]
+    fn test_validate_swap_below_memory() {
+        let mut res = MemoryResources {
+            update_values: true,
+            memory_hard_limit: Some(1024 * 1024 * 1024),
+            memory_swap_limit: Some(1024 * 1024),
+            ..Default::default()
+        };
+        assert_invalid(res.validate(false));
+
+        res.memory_swap_limit = Some(-1);
+        assert!(res.validate(false).is_ok());
+        res.memory_swap_limit = Some(2 * 1024 * 1024 * 1024);
+        assert!(res.validate(false).is_ok());
+
+        res.memory_hard_limit = Some(-2);
+        assert_invalid(res.validate(false));
+    }
+
+    #[test]
+    fn test_validate_swap_without_memory_v2() {
+        let mut res = MemoryResources {
+            update_values: true,
+            memory_swap_limit: Some(1024 * 1024),
+            ..Default::default()
+        };
+        assert!(res.validate(false).is_ok());
+        assert_invalid(res.validate(true));
+
+        res.memory_swap_limit = Some(-1);
+        assert!(res.validate(true).is_ok());
+    }
+
+    #[test]
+    fn test_validate_unified() {
+        let mut res = Resources::default();
+        assert!(res.validate_unified(false).is_ok());
+
+        res.unified.insert("memory.high".to_string(), "max".to_string());
+        assert!(res.validate_unified(true).is_ok());
+        assert_invalid(res.validate_unified(false));
+
+        for key in &["", ".", "..", "../memory.high", "child/memory.high"] {
+            res.unified.clear();
+            res.unified.insert(key.to_string(), "max".to_string());
+            assert_invalid(res.validate_unified(true));
+        }
+    }
+
+    #[test]
+    fn test_validate_blkio_weight() {
+        let mut res = BlkIoResources {
+            update_values: true,
+            weight: Some(5),
+            ..Default::default()
+        };
+        assert_invalid(res.validate());
+
+        res.weight = Some(500);
+        assert!(res.validate().is_ok());
+
+        res.weight_device = vec![BlkIoDeviceResource {
+            major: 8,
+            minor: 0,
+            weight: None,
+            leaf_weight: Some(1001),
+        }];
+        assert_invalid(res.validate());
+    }
+}
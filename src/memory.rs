@@ -2,12 +2,19 @@
 //!
 //! See the Kernel's documentation for more information about this subsystem, found at:
 //!  [Documentation/cgroup-v1/memory.txt](https://www.kernel.org/doc/Documentation/cgroup-v1/memory.txt)
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
+use bitflags::bitflags;
+
+use log::*;
+
 use crate::error::*;
 use crate::error::ErrorKind::*;
+use crate::events::Notifier;
 
 use crate::{
     ControllIdentifier, ControllerInternal, Controllers, MemoryResources, Resources, Subsystem,
@@ -22,10 +29,12 @@ use crate::{
 pub struct MemController {
     base: PathBuf,
     path: PathBuf,
+    v2: bool,
 }
 
 /// Controls statistics and controls about the OOM killer operating in this control group.
 #[derive(Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OomControl {
     /// If true, the OOM killer has been disabled for the tasks in this control group.
     pub oom_kill_disable: bool,
@@ -45,263 +54,364 @@ fn parse_oom_control(s: String) -> Result<OomControl> {
     })
 }
 
+/// The identifier of a NUMA node, i.e. the `0` in `N0`.
+pub type NodeId = u64;
+
+/// A single counter of `memory.numa_stat`: the total number of pages and the same number broken
+/// down by NUMA node.
+///
+/// In the unified hierarchy, the counters are in bytes instead of pages.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumaCounter {
+    /// The number of pages on all nodes together. The unified hierarchy does not report this, so
+    /// it is the sum of `per_node` there.
+    pub total: u64,
+    /// The number of pages on each node.
+    pub per_node: BTreeMap<NodeId, u64>,
+}
+
+bitflags! {
+    /// The charges that are moved along with a task when it is moved into another control group.
+    #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MoveCharge: u64 {
+        /// Move the charges of the anonymous pages (and swap) used by the task.
+        const ANON = 1;
+        /// Move the charges of the file pages (including tmpfs and shared memory) mapped by the
+        /// task.
+        const FILE = 2;
+    }
+}
+
+/// How often the memory usage of a control group hit the boundaries of the unified hierarchy,
+/// as found in `memory.events`.
+#[derive(Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryEvents {
+    /// How often the usage went below `memory.low` although it was reclaimed from.
+    pub low: u64,
+    /// How often the tasks were throttled because the usage was above `memory.high`.
+    pub high: u64,
+    /// How often the usage was about to go above `memory.max`.
+    pub max: u64,
+    /// How often the usage hit the limit and an allocation was about to fail.
+    pub oom: u64,
+    /// How many tasks were killed by the OOM killer.
+    pub oom_kill: u64,
+    /// How often the whole control group was killed by the OOM killer, see `memory.oom.group`.
+    pub oom_group_kill: u64,
+}
+
+/// How often the swap usage of a control group hit its boundaries, as found in
+/// `memory.swap.events`.
+#[derive(Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapEvents {
+    /// How often the allocations were throttled because the swap usage was above
+    /// `memory.swap.high`.
+    pub high: u64,
+    /// How often the swap usage was about to go above `memory.swap.max`.
+    pub max: u64,
+    /// How often swapping out failed, because the swap was full or the limit was hit.
+    pub fail: u64,
+}
+
+/// Parse a flat-keyed file like `memory.events` into its keys and values.
+fn parse_flat_keyed(s: &str) -> Result<Vec<(&str, u64)>> {
+    let mut fin = Vec::new();
+    for line in s.lines() {
+        let mut spl = line.split_whitespace();
+        match (spl.next(), spl.next(), spl.next()) {
+            (Some(key), Some(value), None) => {
+                let value = value
+                    .parse::<u64>()
+                    .map_err(|e| Error::with_cause(ParseError, e))?;
+                fin.push((key, value));
+            }
+            (None, _, _) => continue,
+            _ => return Err(Error::new(ParseError)),
+        }
+    }
+    Ok(fin)
+}
+
+fn parse_memory_events(s: String) -> Result<MemoryEvents> {
+    let mut events = MemoryEvents::default();
+    for (key, value) in parse_flat_keyed(&s)? {
+        match key {
+            "low" => events.low = value,
+            "high" => events.high = value,
+            "max" => events.max = value,
+            "oom" => events.oom = value,
+            "oom_kill" => events.oom_kill = value,
+            "oom_group_kill" => events.oom_group_kill = value,
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+fn parse_swap_events(s: String) -> Result<SwapEvents> {
+    let mut events = SwapEvents::default();
+    for (key, value) in parse_flat_keyed(&s)? {
+        match key {
+            "high" => events.high = value,
+            "max" => events.max = value,
+            "fail" => events.fail = value,
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// The levels of memory pressure that can be listened for with
+/// `MemController::pressure_listener()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PressureLevel {
+    /// The system is reclaiming memory for new allocations, e.g. by shrinking caches.
+    Low,
+    /// The system is swapping or evicting active file caches.
+    Medium,
+    /// The system is thrashing, or is about to run out of memory.
+    Critical,
+}
+
+impl fmt::Display for PressureLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            PressureLevel::Low => "low",
+            PressureLevel::Medium => "medium",
+            PressureLevel::Critical => "critical",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Controls which control groups' memory pressure is reported to a listener.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PressureMode {
+    /// Events are reported for this control group and, unless a listener of a descendant control
+    /// group already received it, for its descendants.
+    Default,
+    /// Events are reported for this control group and all of its descendants, regardless of
+    /// other listeners.
+    Hierarchy,
+    /// Events are only reported when the pressure is in this very control group.
+    Local,
+}
+
+impl fmt::Display for PressureMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            PressureMode::Default => "default",
+            PressureMode::Hierarchy => "hierarchy",
+            PressureMode::Local => "local",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// Contains statistics about the NUMA locality of the control group's tasks.
+///
+/// The counters in `memory.numa_stat` differ between kernel versions, thus every field is `None`
+/// if the kernel did not report it. Counters that are not known to this crate are kept in
+/// `extra`, keyed by their name.
 #[derive(Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NumaStat {
     /// Total amount of pages used by the control group.
-    pub total_pages: u64,
-    /// Total amount of pages used by the control group, broken down by NUMA node.
-    pub total_pages_per_node: Vec<u64>,
+    pub total_pages: Option<NumaCounter>,
     /// Total amount of file pages used by the control group.
-    pub file_pages: u64,
-    /// Total amount of file pages used by the control group, broken down by NUMA node.
-    pub file_pages_per_node: Vec<u64>,
+    pub file_pages: Option<NumaCounter>,
     /// Total amount of anonymous pages used by the control group.
-    pub anon_pages: u64,
-    /// Total amount of anonymous pages used by the control group, broken down by NUMA node.
-    pub anon_pages_per_node: Vec<u64>,
+    pub anon_pages: Option<NumaCounter>,
     /// Total amount of unevictable pages used by the control group.
-    pub unevictable_pages: u64,
-    /// Total amount of unevictable pages used by the control group, broken down by NUMA node.
-    pub unevictable_pages_per_node: Vec<u64>,
+    pub unevictable_pages: Option<NumaCounter>,
 
     /// Same as `total_pages`, but includes the descedant control groups' number as well.
-    pub hierarchical_total_pages: u64,
-    /// Same as `total_pages_per_node`, but includes the descedant control groups' number as well.
-    pub hierarchical_total_pages_per_node: Vec<u64>,
+    pub hierarchical_total_pages: Option<NumaCounter>,
     /// Same as `file_pages`, but includes the descedant control groups' number as well.
-    pub hierarchical_file_pages: u64,
-    /// Same as `file_pages_per_node`, but includes the descedant control groups' number as well.
-    pub hierarchical_file_pages_per_node: Vec<u64>,
+    pub hierarchical_file_pages: Option<NumaCounter>,
     /// Same as `anon_pages`, but includes the descedant control groups' number as well.
-    pub hierarchical_anon_pages: u64,
-    /// Same as `anon_pages_per_node`, but includes the descedant control groups' number as well.
-    pub hierarchical_anon_pages_per_node: Vec<u64>,
-    /// Same as `unevictable`, but includes the descedant control groups' number as well.
-    pub hierarchical_unevictable_pages: u64,
-    /// Same as `unevictable_per_node`, but includes the descedant control groups' number as well.
-    pub hierarchical_unevictable_pages_per_node: Vec<u64>,
+    pub hierarchical_anon_pages: Option<NumaCounter>,
+    /// Same as `unevictable_pages`, but includes the descedant control groups' number as well.
+    pub hierarchical_unevictable_pages: Option<NumaCounter>,
+
+    /// Any other counters found in the file, such as `shmem` or `active_anon`.
+    pub extra: HashMap<String, NumaCounter>,
 }
 
-fn parse_numa_stat(s: String) -> Result<NumaStat> {
-    // Parse the number of nodes
-    let _nodes = (s.split_whitespace().collect::<Vec<_>>().len() - 8) / 8;
-    let mut ls = s.lines();
-    let total_line = ls.next().unwrap();
-    let file_line = ls.next().unwrap();
-    let anon_line = ls.next().unwrap();
-    let unevict_line = ls.next().unwrap();
-    let hier_total_line = ls.next().unwrap();
-    let hier_file_line = ls.next().unwrap();
-    let hier_anon_line = ls.next().unwrap();
-    let hier_unevict_line = ls.next().unwrap();
-
-    Ok(NumaStat {
-        total_pages: total_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        total_pages_per_node: {
-            let spl = &total_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        file_pages: file_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        file_pages_per_node: {
-            let spl = &file_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        anon_pages: anon_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        anon_pages_per_node: {
-            let spl = &anon_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        unevictable_pages: unevict_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        unevictable_pages_per_node: {
-            let spl = &unevict_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        hierarchical_total_pages: hier_total_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        hierarchical_total_pages_per_node: {
-            let spl = &hier_total_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        hierarchical_file_pages: hier_file_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
-            .parse::<u64>()
-            .unwrap_or(0),
-        hierarchical_file_pages_per_node: {
-            let spl = &hier_file_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        hierarchical_anon_pages: hier_anon_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
+/// Parse a line like "total=51189 N0=51189 N1=123" (V1) or "anon N0=4096 N1=0" (V2) into the
+/// name of the counter and its values.
+fn parse_numa_counter(line: &str) -> Result<(String, NumaCounter)> {
+    let mut spl = line.split_whitespace();
+    let (name, total) = match spl.next().map(|x| x.split('=').collect::<Vec<_>>()) {
+        Some(ref kv) if kv.len() == 2 => (kv[0], Some(kv[1])),
+        Some(ref kv) if kv.len() == 1 => (kv[0], None),
+        _ => return Err(Error::new(ParseError)),
+    };
+    let mut counter = NumaCounter::default();
+
+    for node in spl {
+        let kv = node.split('=').collect::<Vec<_>>();
+        if kv.len() != 2 || !kv[0].starts_with('N') {
+            return Err(Error::new(ParseError));
+        }
+        let id = kv[0][1..]
+            .parse::<NodeId>()
+            .map_err(|e| Error::with_cause(ParseError, e))?;
+        let pages = kv[1]
             .parse::<u64>()
-            .unwrap_or(0),
-        hierarchical_anon_pages_per_node: {
-            let spl = &hier_anon_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-        hierarchical_unevictable_pages: hier_unevict_line
-            .split(|x| x == ' ' || x == '=')
-            .collect::<Vec<_>>()[1]
+            .map_err(|e| Error::with_cause(ParseError, e))?;
+        counter.per_node.insert(id, pages);
+    }
+
+    counter.total = match total {
+        Some(total) => total
             .parse::<u64>()
-            .unwrap_or(0),
-        hierarchical_unevictable_pages_per_node: {
-            let spl = &hier_unevict_line.split(" ").collect::<Vec<_>>()[1..];
-            spl.iter()
-                .map(|x| {
-                    x.split("=").collect::<Vec<_>>()[1]
-                        .parse::<u64>()
-                        .unwrap_or(0)
-                }).collect()
-        },
-    })
+            .map_err(|e| Error::with_cause(ParseError, e))?,
+        // V2 only reports the counter per node
+        None => counter.per_node.values().sum(),
+    };
+
+    Ok((name.to_string(), counter))
+}
+
+fn parse_numa_stat(s: String) -> Result<NumaStat> {
+    let mut stat = NumaStat::default();
+
+    for line in s.lines().filter(|l| !l.trim().is_empty()) {
+        let (name, counter) = parse_numa_counter(line)?;
+        match name.as_ref() {
+            "total" => stat.total_pages = Some(counter),
+            "file" => stat.file_pages = Some(counter),
+            "anon" => stat.anon_pages = Some(counter),
+            "unevictable" => stat.unevictable_pages = Some(counter),
+            "hierarchical_total" => stat.hierarchical_total_pages = Some(counter),
+            "hierarchical_file" => stat.hierarchical_file_pages = Some(counter),
+            "hierarchical_anon" => stat.hierarchical_anon_pages = Some(counter),
+            "hierarchical_unevictable" => stat.hierarchical_unevictable_pages = Some(counter),
+            _ => {
+                stat.extra.insert(name, counter);
+            }
+        }
+    }
+
+    Ok(stat)
 }
 
+/// The statistics found in `memory.stat`.
+///
+/// The set of keys in this file differs between kernel versions, thus every field is `None` if
+/// the kernel did not report it. Keys that are not known to this crate are kept in `extra`.
 #[derive(Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryStat {
-    pub cache: u64,
-    pub rss: u64,
-    pub rss_huge: u64,
-    pub shmem: u64,
-    pub mapped_file: u64,
-    pub dirty: u64,
-    pub writeback: u64,
-    pub swap: u64,
-    pub pgpgin: u64,
-    pub pgpgout: u64,
-    pub pgfault: u64,
-    pub pgmajfault: u64,
-    pub inactive_anon: u64,
-    pub active_anon: u64,
-    pub inactive_file: u64,
-    pub active_file: u64,
-    pub unevictable: u64,
-    pub hierarchical_memory_limit: u64,
-    pub hierarchical_memsw_limit: u64,
-    pub total_cache: u64,
-    pub total_rss: u64,
-    pub total_rss_huge: u64,
-    pub total_shmem: u64,
-    pub total_mapped_file: u64,
-    pub total_dirty: u64,
-    pub total_writeback: u64,
-    pub total_swap: u64,
-    pub total_pgpgin: u64,
-    pub total_pgpgout: u64,
-    pub total_pgfault: u64,
-    pub total_pgmajfault: u64,
-    pub total_inactive_anon: u64,
-    pub total_active_anon: u64,
-    pub total_inactive_file: u64,
-    pub total_active_file: u64,
-    pub total_unevictable: u64,
+    pub cache: Option<u64>,
+    pub rss: Option<u64>,
+    pub rss_huge: Option<u64>,
+    pub shmem: Option<u64>,
+    pub mapped_file: Option<u64>,
+    pub dirty: Option<u64>,
+    pub writeback: Option<u64>,
+    pub swap: Option<u64>,
+    pub pgpgin: Option<u64>,
+    pub pgpgout: Option<u64>,
+    pub pgfault: Option<u64>,
+    pub pgmajfault: Option<u64>,
+    pub inactive_anon: Option<u64>,
+    pub active_anon: Option<u64>,
+    pub inactive_file: Option<u64>,
+    pub active_file: Option<u64>,
+    pub unevictable: Option<u64>,
+    pub hierarchical_memory_limit: Option<u64>,
+    pub hierarchical_memsw_limit: Option<u64>,
+    pub total_cache: Option<u64>,
+    pub total_rss: Option<u64>,
+    pub total_rss_huge: Option<u64>,
+    pub total_shmem: Option<u64>,
+    pub total_mapped_file: Option<u64>,
+    pub total_dirty: Option<u64>,
+    pub total_writeback: Option<u64>,
+    pub total_swap: Option<u64>,
+    pub total_pgpgin: Option<u64>,
+    pub total_pgpgout: Option<u64>,
+    pub total_pgfault: Option<u64>,
+    pub total_pgmajfault: Option<u64>,
+    pub total_inactive_anon: Option<u64>,
+    pub total_active_anon: Option<u64>,
+    pub total_inactive_file: Option<u64>,
+    pub total_active_file: Option<u64>,
+    pub total_unevictable: Option<u64>,
+    /// Any other keys found in the file, along with their values.
+    pub extra: HashMap<String, u64>,
 }
 
 fn parse_memory_stat(s: String) -> Result<MemoryStat> {
-    let sp: Vec<&str> = s
-        .split_whitespace()
-        .filter(|x| x.parse::<u64>().is_ok())
-        .collect();
-
-    let mut spl = sp.iter();
-    Ok(MemoryStat {
-        cache: spl.next().unwrap().parse::<u64>().unwrap(),
-        rss: spl.next().unwrap().parse::<u64>().unwrap(),
-        rss_huge: spl.next().unwrap().parse::<u64>().unwrap(),
-        shmem: spl.next().unwrap().parse::<u64>().unwrap(),
-        mapped_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        dirty: spl.next().unwrap().parse::<u64>().unwrap(),
-        writeback: spl.next().unwrap().parse::<u64>().unwrap(),
-        swap: spl.next().unwrap().parse::<u64>().unwrap(),
-        pgpgin: spl.next().unwrap().parse::<u64>().unwrap(),
-        pgpgout: spl.next().unwrap().parse::<u64>().unwrap(),
-        pgfault: spl.next().unwrap().parse::<u64>().unwrap(),
-        pgmajfault: spl.next().unwrap().parse::<u64>().unwrap(),
-        inactive_anon: spl.next().unwrap().parse::<u64>().unwrap(),
-        active_anon: spl.next().unwrap().parse::<u64>().unwrap(),
-        inactive_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        active_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        unevictable: spl.next().unwrap().parse::<u64>().unwrap(),
-        hierarchical_memory_limit: spl.next().unwrap().parse::<u64>().unwrap(),
-        hierarchical_memsw_limit: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_cache: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_rss: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_rss_huge: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_shmem: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_mapped_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_dirty: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_writeback: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_swap: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_pgpgin: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_pgpgout: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_pgfault: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_pgmajfault: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_inactive_anon: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_active_anon: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_inactive_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_active_file: spl.next().unwrap().parse::<u64>().unwrap(),
-        total_unevictable: spl.next().unwrap().parse::<u64>().unwrap(),
-    })
+    let mut stat = MemoryStat::default();
+
+    for line in s.lines() {
+        let mut spl = line.split_whitespace();
+        let (key, value) = match (spl.next(), spl.next(), spl.next()) {
+            (Some(key), Some(value), None) => (key, value),
+            (None, _, _) => continue,
+            _ => return Err(Error::new(ParseError)),
+        };
+        let value = value
+            .parse::<u64>()
+            .map_err(|e| Error::with_cause(ParseError, e))?;
+
+        match key {
+            "cache" => stat.cache = Some(value),
+            "rss" => stat.rss = Some(value),
+            "rss_huge" => stat.rss_huge = Some(value),
+            "shmem" => stat.shmem = Some(value),
+            "mapped_file" => stat.mapped_file = Some(value),
+            "dirty" => stat.dirty = Some(value),
+            "writeback" => stat.writeback = Some(value),
+            "swap" => stat.swap = Some(value),
+            "pgpgin" => stat.pgpgin = Some(value),
+            "pgpgout" => stat.pgpgout = Some(value),
+            "pgfault" => stat.pgfault = Some(value),
+            "pgmajfault" => stat.pgmajfault = Some(value),
+            "inactive_anon" => stat.inactive_anon = Some(value),
+            "active_anon" => stat.active_anon = Some(value),
+            "inactive_file" => stat.inactive_file = Some(value),
+            "active_file" => stat.active_file = Some(value),
+            "unevictable" => stat.unevictable = Some(value),
+            "hierarchical_memory_limit" => stat.hierarchical_memory_limit = Some(value),
+            "hierarchical_memsw_limit" => stat.hierarchical_memsw_limit = Some(value),
+            "total_cache" => stat.total_cache = Some(value),
+            "total_rss" => stat.total_rss = Some(value),
+            "total_rss_huge" => stat.total_rss_huge = Some(value),
+            "total_shmem" => stat.total_shmem = Some(value),
+            "total_mapped_file" => stat.total_mapped_file = Some(value),
+            "total_dirty" => stat.total_dirty = Some(value),
+            "total_writeback" => stat.total_writeback = Some(value),
+            "total_swap" => stat.total_swap = Some(value),
+            "total_pgpgin" => stat.total_pgpgin = Some(value),
+            "total_pgpgout" => stat.total_pgpgout = Some(value),
+            "total_pgfault" => stat.total_pgfault = Some(value),
+            "total_pgmajfault" => stat.total_pgmajfault = Some(value),
+            "total_inactive_anon" => stat.total_inactive_anon = Some(value),
+            "total_active_anon" => stat.total_active_anon = Some(value),
+            "total_inactive_file" => stat.total_inactive_file = Some(value),
+            "total_active_file" => stat.total_active_file = Some(value),
+            "total_unevictable" => stat.total_unevictable = Some(value),
+            _ => {
+                stat.extra.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    Ok(stat)
 }
 
 /// Contains statistics about the current usage of memory and swap (together, not seperately) by
 /// the control group's tasks.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemSwap {
     /// How many times the limit has been hit.
     pub fail_cnt: u64,
@@ -316,6 +426,7 @@ pub struct MemSwap {
 /// State of and statistics gathered by the kernel about the memory usage of the control group's
 /// tasks.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memory {
     /// How many times the limit has been hit.
     pub fail_cnt: u64,
@@ -325,8 +436,8 @@ pub struct Memory {
     pub usage_in_bytes: u64,
     /// The maximum observed usage of memory by the control group's tasks.
     pub max_usage_in_bytes: u64,
-    /// Whether moving charges at immigrate is allowed.
-    pub move_charge_at_immigrate: u64,
+    /// Which charges are moved along with a task when it is moved into the control group.
+    pub move_charge_at_immigrate: MoveCharge,
     /// Contains various statistics about the NUMA locality of the control group's tasks.
     ///
     /// The format of this field (as lifted from the kernel sources):
@@ -354,12 +465,13 @@ pub struct Memory {
     pub swappiness: u64,
     /// If set, then under OOM conditions, the kernel will try to reclaim memory from the children
     /// of the offending process too. By default, this is not allowed.
-    pub use_hierarchy: u64,
+    pub use_hierarchy: bool,
 }
 
 /// The current state of and gathered statistics about the kernel's memory usage for TCP-related
 /// data structures.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tcp {
     /// How many times the limit has been hit.
     pub fail_cnt: u64,
@@ -378,6 +490,7 @@ pub struct Tcp {
 /// these tasks if it would think that the limits here would be violated. It's important to note
 /// that interrupts in particular might not be able to enforce these limits.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Kmem {
     /// How many times the limit has been hit.
     pub fail_cnt: u64,
@@ -388,7 +501,72 @@ pub struct Kmem {
     /// The maximum observed usage of kernel memory used by the control group's tasks, in bytes.
     pub max_usage_in_bytes: u64,
     /// Contains information about the memory usage of the kernel's caches, per control group.
-    pub slabinfo: String,
+    pub slabinfo: Vec<SlabEntry>,
+}
+
+impl Kmem {
+    /// Returns the `n` caches of `slabinfo` that take up the most memory, largest first.
+    pub fn top_slabs(&self, n: usize) -> Vec<&SlabEntry> {
+        let mut slabs = self.slabinfo.iter().collect::<Vec<_>>();
+        slabs.sort_by_key(|slab| std::cmp::Reverse(slab.bytes()));
+        slabs.truncate(n);
+        slabs
+    }
+}
+
+/// A single cache of the kernel, as found in `memory.kmem.slabinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlabEntry {
+    /// The name of the cache, e.g. `dentry`.
+    pub name: String,
+    /// The number of objects that are currently in use.
+    pub active_objs: u64,
+    /// The number of objects that are allocated, whether in use or not.
+    pub num_objs: u64,
+    /// The size of a single object, in bytes.
+    pub objsize: u64,
+    /// How many objects fit into a single slab.
+    pub objperslab: u64,
+    /// How many pages a single slab takes up.
+    pub pagesperslab: u64,
+}
+
+impl SlabEntry {
+    /// The memory taken up by all allocated objects of the cache, in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.num_objs * self.objsize
+    }
+}
+
+/// Parse `memory.kmem.slabinfo`, which has the same format as `/proc/slabinfo`.
+fn parse_slabinfo(s: String) -> Result<Vec<SlabEntry>> {
+    let mut fin = Vec::new();
+    for line in s.lines() {
+        // skip the version and the column headers
+        if line.starts_with("slabinfo") || line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let spl = line.split_whitespace().collect::<Vec<_>>();
+        if spl.len() < 6 {
+            return Err(Error::new(ParseError));
+        }
+        let mut values = [0u64; 5];
+        for (value, field) in values.iter_mut().zip(&spl[1..6]) {
+            *value = field
+                .parse()
+                .map_err(|e| Error::with_cause(ParseError, e))?;
+        }
+        fin.push(SlabEntry {
+            name: spl[0].to_string(),
+            active_objs: values[0],
+            num_objs: values[1],
+            objsize: values[2],
+            objperslab: values[3],
+            pagesperslab: values[4],
+        });
+    }
+    Ok(fin)
 }
 
 impl ControllerInternal for MemController {
@@ -404,18 +582,70 @@ impl ControllerInternal for MemController {
     fn get_base(&self) -> &PathBuf {
         &self.base
     }
+    fn is_v2(&self) -> bool {
+        self.v2
+    }
 
     fn apply(&self, res: &Resources) -> Result<()> {
         // get the resources that apply to this controller
         let memres: &MemoryResources = &res.memory;
 
+        if memres.update_values && self.v2 {
+            return self.apply_v2(memres);
+        }
+
         if memres.update_values {
-            let _ = self.set_limit(memres.memory_hard_limit);
-            let _ = self.set_soft_limit(memres.memory_soft_limit);
-            let _ = self.set_kmem_limit(memres.kernel_memory_limit);
-            let _ = self.set_memswap_limit(memres.memory_swap_limit);
-            let _ = self.set_tcp_limit(memres.kernel_tcp_memory_limit);
-            let _ = self.set_swappiness(memres.swappiness);
+            let unsupported = [
+                ("memory_min", memres.memory_min),
+                ("memory_low", memres.memory_low),
+                ("memory_high", memres.memory_high),
+                ("swap_high", memres.swap_high),
+                ("zswap_max", memres.zswap_max),
+            ];
+            for &(name, value) in unsupported.iter() {
+                if value.is_some() {
+                    warn!("{} is only supported in the unified hierarchy, ignoring", name);
+                }
+            }
+            if memres.oom_group.is_some() {
+                warn!("oom_group is only supported in the unified hierarchy, ignoring");
+            }
+
+            if let Some(limit) = memres.memory_hard_limit {
+                if let Err(e) = self.set_limit(limit) {
+                    // the kernel refuses a memory limit above the memory+swap limit, so when
+                    // both are raised, the memory+swap limit has to go first
+                    match memres.memory_swap_limit {
+                        Some(swap) => self.set_memswap_limit(swap)?,
+                        None => return Err(e),
+                    }
+                    self.set_limit(limit)?;
+                }
+            }
+            if let Some(limit) = memres.memory_soft_limit {
+                self.set_soft_limit(limit)?;
+            }
+            if let Some(limit) = memres.kernel_memory_limit {
+                self.set_kmem_limit(limit)?;
+            }
+            if let Some(limit) = memres.memory_swap_limit {
+                self.set_memswap_limit(limit)?;
+            }
+            if let Some(limit) = memres.kernel_tcp_memory_limit {
+                self.set_tcp_limit(limit)?;
+            }
+            if let Some(swappiness) = memres.swappiness {
+                self.set_swappiness(swappiness)?;
+            }
+            if let Some(b) = memres.oom_kill_disable {
+                self.set_oom_kill_disable(b)?;
+            }
+            if let Some(b) = memres.use_hierarchy {
+                self.set_use_hierarchy(b)?;
+            }
+            if let Some(flags) = memres.move_charge_at_immigrate {
+                self.set_move_charge_at_immigrate(flags)?;
+            }
         }
 
         Ok(())
@@ -423,6 +653,67 @@ impl ControllerInternal for MemController {
 }
 
 impl MemController {
+    fn apply_v2(&self, memres: &MemoryResources) -> Result<()> {
+        let unsupported = [
+            ("kernel_memory_limit", memres.kernel_memory_limit.is_some()),
+            ("kernel_tcp_memory_limit", memres.kernel_tcp_memory_limit.is_some()),
+            ("swappiness", memres.swappiness.is_some()),
+            ("oom_kill_disable", memres.oom_kill_disable.is_some()),
+            ("use_hierarchy", memres.use_hierarchy.is_some()),
+            ("move_charge_at_immigrate", memres.move_charge_at_immigrate.is_some()),
+        ];
+        for &(name, set) in unsupported.iter() {
+            if set {
+                warn!("{} is not supported in the unified hierarchy, ignoring", name);
+            }
+        }
+
+        if let Some(limit) = memres.memory_hard_limit {
+            self.set_max(limit)?;
+        }
+        // the soft limit is the closest there is to memory.low
+        if let Some(limit) = memres.memory_low.or(memres.memory_soft_limit) {
+            self.set_low(limit)?;
+        }
+        if let Some(limit) = memres.memory_min {
+            self.set_min(limit)?;
+        }
+        if let Some(limit) = memres.memory_high {
+            self.set_high(limit)?;
+        }
+        if let Some(limit) = memres.memory_swap_limit {
+            // memory.swap.max limits the swap alone, not memory and swap together
+            let swap = match (limit, memres.memory_hard_limit) {
+                (-1, _) => -1,
+                (limit, Some(mem)) if mem >= 0 && limit >= mem => limit - mem,
+                (limit, Some(mem)) if mem >= 0 => {
+                    return Err(Error::new(InvalidResources(format!(
+                        "memory+swap limit ({}) must not be smaller than the memory limit ({})",
+                        limit, mem
+                    ))));
+                }
+                (limit, _) => {
+                    return Err(Error::new(InvalidResources(format!(
+                        "memory+swap limit ({}) requires a memory limit in the unified hierarchy",
+                        limit
+                    ))));
+                }
+            };
+            self.set_swap_max(swap)?;
+        }
+        if let Some(limit) = memres.swap_high {
+            self.set_swap_high(limit)?;
+        }
+        if let Some(limit) = memres.zswap_max {
+            self.set_zswap_max(limit)?;
+        }
+        if let Some(b) = memres.oom_group {
+            self.set_oom_group(b)?;
+        }
+
+        Ok(())
+    }
+
     /// Contructs a new `MemController` with `oroot` serving as the root of the control group.
     pub fn new(oroot: PathBuf) -> Self {
         let mut root = oroot;
@@ -430,6 +721,17 @@ impl MemController {
         Self {
             base: root.clone(),
             path: root,
+            v2: false,
+        }
+    }
+
+    /// Constructs a new `MemController` for the unified hierarchy, with `root` being the root directory
+    /// of the hierarchy.
+    pub fn new_v2(root: PathBuf) -> Self {
+        Self {
+            base: root.clone(),
+            path: root,
+            v2: true,
         }
     }
 
@@ -459,12 +761,19 @@ impl MemController {
             move_charge_at_immigrate: self
                 .open_path("memory.move_charge_at_immigrate", false)
                 .and_then(read_u64_from)
-                .unwrap_or(0),
+                .map(MoveCharge::from_bits_truncate)
+                .unwrap_or(MoveCharge::empty()),
             numa_stat: self
                 .open_path("memory.numa_stat", false)
                 .and_then(read_string_from)
                 .and_then(parse_numa_stat)
-                .unwrap_or(NumaStat::default()),
+                .unwrap_or_else(|e| {
+                    // the file is missing on kernels without NUMA support
+                    if *e.kind() == ParseError {
+                        warn!("failed to parse memory.numa_stat: {}", e);
+                    }
+                    NumaStat::default()
+                }),
             oom_control: self
                 .open_path("memory.oom_control", false)
                 .and_then(read_string_from)
@@ -486,7 +795,8 @@ impl MemController {
             use_hierarchy: self
                 .open_path("memory.use_hierarchy", false)
                 .and_then(read_u64_from)
-                .unwrap_or(0),
+                .map(|x| x == 1)
+                .unwrap_or(false),
         }
     }
 
@@ -512,7 +822,8 @@ impl MemController {
             slabinfo: self
                 .open_path("memory.kmem.slabinfo", false)
                 .and_then(read_string_from)
-                .unwrap_or("".to_string()),
+                .and_then(parse_slabinfo)
+                .unwrap_or_default(),
         }
     }
 
@@ -562,6 +873,48 @@ impl MemController {
         }
     }
 
+    /// The current memory usage of the control group, from `memory.usage_in_bytes` or, in the
+    /// unified hierarchy, `memory.current`.
+    fn current_usage(&self) -> Result<u64> {
+        if !self.v2 {
+            self.open_path("memory.usage_in_bytes", false)
+                .and_then(read_u64_from)
+        } else {
+            self.open_path("memory.current", false)
+                .and_then(read_u64_from)
+        }
+    }
+
+    /// Reclaim as much memory from the control group as possible, without killing any of its
+    /// tasks. Returns by how many bytes the memory usage dropped.
+    ///
+    /// This is only available in the V1 hierarchy (`memory.force_empty`), see `reclaim()` for the
+    /// unified hierarchy.
+    pub fn force_empty(&self) -> Result<u64> {
+        let before = self.current_usage()?;
+        self.open_path("memory.force_empty", true)
+            .and_then(|mut file| {
+                file.write_all(b"0")
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })?;
+        Ok(before.saturating_sub(self.current_usage()?))
+    }
+
+    /// Reclaim `bytes` bytes of memory from the control group. Returns by how many bytes the
+    /// memory usage dropped.
+    ///
+    /// Fails if the kernel could not reclaim the requested amount. This is only available in the
+    /// unified hierarchy (`memory.reclaim`), see `force_empty()` for the V1 hierarchy.
+    pub fn reclaim(&self, bytes: u64) -> Result<u64> {
+        let before = self.current_usage()?;
+        self.open_path("memory.reclaim", true)
+            .and_then(|mut file| {
+                file.write_all(bytes.to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })?;
+        Ok(before.saturating_sub(self.current_usage()?))
+    }
+
     /// Reset the fail counter
     pub fn reset_fail_count(&self) -> Result<()> {
         self.open_path("memory.failcnt", true)
@@ -598,8 +951,69 @@ impl MemController {
             })
     }
 
+    /// Reset the maximum observed memory usage
+    pub fn reset_max_usage(&self) -> Result<()> {
+        self.open_path("memory.max_usage_in_bytes", true)
+            .and_then(|mut file| {
+                file.write_all("0".to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Reset the maximum observed kernel memory usage
+    pub fn reset_kmem_max_usage(&self) -> Result<()> {
+        self.open_path("memory.kmem.max_usage_in_bytes", true)
+            .and_then(|mut file| {
+                file.write_all("0".to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Reset the maximum observed TCP related memory usage
+    pub fn reset_tcp_max_usage(&self) -> Result<()> {
+        self.open_path("memory.kmem.tcp.max_usage_in_bytes", true)
+            .and_then(|mut file| {
+                file.write_all("0".to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Reset the maximum observed memory+swap usage
+    pub fn reset_memswap_max_usage(&self) -> Result<()> {
+        self.open_path("memory.memsw.max_usage_in_bytes", true)
+            .and_then(|mut file| {
+                file.write_all("0".to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Reset all of the maximum observed usages that the kernel provides for the control group,
+    /// see the `reset_*_max_usage()` functions.
+    ///
+    /// In the unified hierarchy, a write to `memory.peak` or `memory.swap.peak` only resets the
+    /// peak for later reads through the same open file, so there is nothing this function could
+    /// reset and it does nothing.
+    pub fn reset_peaks(&self) -> Result<()> {
+        if self.v2 {
+            return Ok(());
+        }
+        self.reset_max_usage()?;
+        if self.get_path().join("memory.kmem.max_usage_in_bytes").exists() {
+            self.reset_kmem_max_usage()?;
+        }
+        if self.get_path().join("memory.kmem.tcp.max_usage_in_bytes").exists() {
+            self.reset_tcp_max_usage()?;
+        }
+        if self.get_path().join("memory.memsw.max_usage_in_bytes").exists() {
+            self.reset_memswap_max_usage()?;
+        }
+        Ok(())
+    }
+
     /// Set the memory usage limit of the control group, in bytes.
-    pub fn set_limit(&self, limit: u64) -> Result<()> {
+    ///
+    /// Setting the limit to `-1` removes it.
+    pub fn set_limit(&self, limit: i64) -> Result<()> {
         self.open_path("memory.limit_in_bytes", true)
             .and_then(|mut file| {
                 file.write_all(limit.to_string().as_ref())
@@ -608,7 +1022,7 @@ impl MemController {
     }
 
     /// Set the kernel memory limit of the control group, in bytes.
-    pub fn set_kmem_limit(&self, limit: u64) -> Result<()> {
+    pub fn set_kmem_limit(&self, limit: i64) -> Result<()> {
         self.open_path("memory.kmem.limit_in_bytes", true)
             .and_then(|mut file| {
                 file.write_all(limit.to_string().as_ref())
@@ -617,7 +1031,7 @@ impl MemController {
     }
 
     /// Set the memory+swap limit of the control group, in bytes.
-    pub fn set_memswap_limit(&self, limit: u64) -> Result<()> {
+    pub fn set_memswap_limit(&self, limit: i64) -> Result<()> {
         self.open_path("memory.memsw.limit_in_bytes", true)
             .and_then(|mut file| {
                 file.write_all(limit.to_string().as_ref())
@@ -626,7 +1040,7 @@ impl MemController {
     }
 
     /// Set how much kernel memory can be used for TCP-related buffers by the control group.
-    pub fn set_tcp_limit(&self, limit: u64) -> Result<()> {
+    pub fn set_tcp_limit(&self, limit: i64) -> Result<()> {
         self.open_path("memory.kmem.tcp.limit_in_bytes", true)
             .and_then(|mut file| {
                 file.write_all(limit.to_string().as_ref())
@@ -638,7 +1052,7 @@ impl MemController {
     ///
     /// This limit is enforced when the system is nearing OOM conditions. Contrast this with the
     /// hard limit, which is _always_ enforced.
-    pub fn set_soft_limit(&self, limit: u64) -> Result<()> {
+    pub fn set_soft_limit(&self, limit: i64) -> Result<()> {
         self.open_path("memory.soft_limit_in_bytes", true)
             .and_then(|mut file| {
                 file.write_all(limit.to_string().as_ref())
@@ -657,6 +1071,277 @@ impl MemController {
                     .map_err(|e| Error::with_cause(WriteFailed, e))
             })
     }
+
+    /// Disable (or re-enable) the OOM killer for the control group's tasks.
+    ///
+    /// When disabled, tasks that would be killed are instead paused until memory is freed.
+    pub fn set_oom_kill_disable(&self, b: bool) -> Result<()> {
+        self.open_path("memory.oom_control", true)
+            .and_then(|mut file| {
+                if b {
+                    file.write_all(b"1").map_err(|e| Error::with_cause(WriteFailed, e))
+                } else {
+                    file.write_all(b"0").map_err(|e| Error::with_cause(WriteFailed, e))
+                }
+            })
+    }
+
+    /// Whether the tasks of the control group are killed together when the OOM killer picks one
+    /// of them.
+    ///
+    /// This is only available in the unified hierarchy (`memory.oom.group`).
+    pub fn oom_group(&self) -> Result<bool> {
+        self.open_path("memory.oom.group", false)
+            .and_then(read_u64_from)
+            .map(|x| x == 1)
+    }
+
+    /// Make the OOM killer kill all tasks of the control group together (or not), instead of
+    /// picking a single one.
+    ///
+    /// This is only available in the unified hierarchy (`memory.oom.group`).
+    pub fn set_oom_group(&self, b: bool) -> Result<()> {
+        self.open_path("memory.oom.group", true)
+            .and_then(|mut file| {
+                if b {
+                    file.write_all(b"1").map_err(|e| Error::with_cause(WriteFailed, e))
+                } else {
+                    file.write_all(b"0").map_err(|e| Error::with_cause(WriteFailed, e))
+                }
+            })
+    }
+
+    /// Control whether the memory usage of descendant control groups is accounted to this control
+    /// group as well.
+    ///
+    /// Note that the kernel refuses to change this once the control group has children.
+    pub fn set_use_hierarchy(&self, b: bool) -> Result<()> {
+        self.open_path("memory.use_hierarchy", true)
+            .and_then(|mut file| {
+                if b {
+                    file.write_all(b"1").map_err(|e| Error::with_cause(WriteFailed, e))
+                } else {
+                    file.write_all(b"0").map_err(|e| Error::with_cause(WriteFailed, e))
+                }
+            })
+    }
+
+    /// Set which charges are moved along with a task when it is moved into this control group.
+    ///
+    /// Note that recent kernels no longer support moving charges and reject anything but an empty
+    /// set.
+    pub fn set_move_charge_at_immigrate(&self, flags: MoveCharge) -> Result<()> {
+        self.open_path("memory.move_charge_at_immigrate", true)
+            .and_then(|mut file| {
+                file.write_all(flags.bits().to_string().as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
+
+    /// Returns a notifier that receives an event whenever the memory usage of the control group
+    /// crosses `bytes`, in either direction.
+    ///
+    /// The threshold is unregistered when the notifier is dropped.
+    pub fn register_threshold(&self, bytes: u64) -> Result<Notifier> {
+        let target = self.open_path("memory.usage_in_bytes", false)?;
+        let event_control = self.open_path("cgroup.event_control", true)?;
+        Notifier::register(target, event_control, &bytes.to_string())
+    }
+
+    /// Same as `register_threshold()`, but for the memory and swap usage together.
+    pub fn register_memsw_threshold(&self, bytes: u64) -> Result<Notifier> {
+        let target = self.open_path("memory.memsw.usage_in_bytes", false)?;
+        let event_control = self.open_path("cgroup.event_control", true)?;
+        Notifier::register(target, event_control, &bytes.to_string())
+    }
+
+    /// Returns a notifier that receives an event whenever the memory pressure in the control group
+    /// reaches `level`. See `PressureMode` for which control groups are taken into account.
+    pub fn pressure_listener(&self, level: PressureLevel, mode: PressureMode) -> Result<Notifier> {
+        let target = self.open_path("memory.pressure_level", false)?;
+        let event_control = self.open_path("cgroup.event_control", true)?;
+        Notifier::register(
+            target,
+            event_control,
+            &format!("{},{}", level, mode),
+        )
+    }
+
+    /// Returns a notifier that receives an event whenever the tasks of the control group run out
+    /// of memory, i.e. whenever the OOM killer is invoked, or the tasks are paused if the OOM
+    /// killer is disabled.
+    ///
+    /// In the unified hierarchy, where `memory.oom_control` does not exist, the `oom` counter of
+    /// `memory.events` is watched instead. Its file descriptor also becomes readable when the other
+    /// counters change, see `Notifier::try_wait()`.
+    pub fn oom_notifier(&self) -> Result<Notifier> {
+        if !self.v2 {
+            let target = self.open_path("memory.oom_control", false)?;
+            let event_control = self.open_path("cgroup.event_control", true)?;
+            Notifier::register(target, event_control, "")
+        } else {
+            self.verify_path()?;
+            Notifier::watch(self.get_path().join("memory.events"), "oom")
+        }
+    }
+
+    /// The amount of memory that is protected from reclaim, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.min`).
+    pub fn min(&self) -> Result<i64> {
+        self.open_path("memory.min", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Protect `bytes` bytes of memory of the control group from being reclaimed.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.min`).
+    pub fn set_min(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.min", bytes)
+    }
+
+    /// The amount of memory that is only reclaimed if there is no unprotected memory left to
+    /// reclaim, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.low`).
+    pub fn low(&self) -> Result<i64> {
+        self.open_path("memory.low", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Protect `bytes` bytes of memory of the control group from reclaim, unless there is no
+    /// unprotected memory left to reclaim.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.low`).
+    pub fn set_low(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.low", bytes)
+    }
+
+    /// The memory usage above which the control group's tasks are throttled and put under heavy
+    /// reclaim pressure, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.high`).
+    pub fn high(&self) -> Result<i64> {
+        self.open_path("memory.high", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Set the memory usage above which the control group's tasks are throttled and put under heavy
+    /// reclaim pressure.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.high`).
+    pub fn set_high(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.high", bytes)
+    }
+
+    /// The hard limit of the memory usage of the control group, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.max`).
+    pub fn max(&self) -> Result<i64> {
+        self.open_path("memory.max", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Set the hard limit of the memory usage of the control group. If it cannot be kept, the OOM
+    /// killer is invoked.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.max`).
+    pub fn set_max(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.max", bytes)
+    }
+
+    /// The hard limit of the swap usage of the control group, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.swap.max`).
+    pub fn swap_max(&self) -> Result<i64> {
+        self.open_path("memory.swap.max", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Set the hard limit of the swap usage of the control group.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.swap.max`).
+    pub fn set_swap_max(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.swap.max", bytes)
+    }
+
+    /// The swap usage above which the control group's allocations are throttled, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.swap.high`).
+    pub fn swap_high(&self) -> Result<i64> {
+        self.open_path("memory.swap.high", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Set the swap usage above which the control group's allocations are throttled.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.swap.high`).
+    pub fn set_swap_high(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.swap.high", bytes)
+    }
+
+    /// The hard limit of the zswap usage of the control group, in bytes.
+    ///
+    /// `-1` means that there is no limit. This is only available in the unified hierarchy
+    /// (`memory.zswap.max`).
+    pub fn zswap_max(&self) -> Result<i64> {
+        self.open_path("memory.zswap.max", false).and_then(read_i64_or_max_from)
+    }
+
+    /// Set the hard limit of the zswap usage of the control group.
+    ///
+    /// Setting `bytes` to `-1` removes the limit. This is only available in the unified hierarchy
+    /// (`memory.zswap.max`).
+    pub fn set_zswap_max(&self, bytes: i64) -> Result<()> {
+        self.write_i64_or_max("memory.zswap.max", bytes)
+    }
+
+    /// Returns how often the memory usage of the control group, or one of its descendants, hit the
+    /// various boundaries.
+    ///
+    /// This is only available in the unified hierarchy (`memory.events`).
+    pub fn events(&self) -> Result<MemoryEvents> {
+        self.open_path("memory.events", false)
+            .and_then(read_string_from)
+            .and_then(parse_memory_events)
+    }
+
+    /// Same as `events()`, but only counts the events of this very control group.
+    ///
+    /// This is only available in the unified hierarchy (`memory.events.local`).
+    pub fn events_local(&self) -> Result<MemoryEvents> {
+        self.open_path("memory.events.local", false)
+            .and_then(read_string_from)
+            .and_then(parse_memory_events)
+    }
+
+    /// Returns how often the swap usage of the control group hit the various boundaries.
+    ///
+    /// This is only available in the unified hierarchy (`memory.swap.events`).
+    pub fn swap_events(&self) -> Result<SwapEvents> {
+        self.open_path("memory.swap.events", false)
+            .and_then(read_string_from)
+            .and_then(parse_swap_events)
+    }
+
+    fn write_i64_or_max(&self, file: &str, value: i64) -> Result<()> {
+        let value = if value < 0 {
+            "max".to_string()
+        } else {
+            value.to_string()
+        };
+        self.open_path(file, true)
+            .and_then(|mut file| {
+                file.write_all(value.as_ref())
+                    .map_err(|e| Error::with_cause(WriteFailed, e))
+            })
+    }
 }
 
 impl ControllIdentifier for MemController {
@@ -679,6 +1364,17 @@ impl<'a> From<&'a Subsystem> for &'a MemController {
     }
 }
 
+/// Read a value that is either a number or `"max"`, which is returned as `-1`.
+fn read_i64_or_max_from(file: File) -> Result<i64> {
+    read_string_from(file).and_then(|s| {
+        if s == "max" {
+            Ok(-1)
+        } else {
+            s.parse().map_err(|e| Error::with_cause(ParseError, e))
+        }
+    })
+}
+
 fn read_u64_from(mut file: File) -> Result<u64> {
     let mut string = String::new();
     match file.read_to_string(&mut string) {
@@ -697,8 +1393,12 @@ fn read_string_from(mut file: File) -> Result<String> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::memory::{
-        parse_memory_stat, parse_numa_stat, parse_oom_control, MemoryStat, NumaStat, OomControl,
+        parse_memory_events, parse_memory_stat, parse_numa_stat, parse_oom_control,
+        parse_slabinfo, parse_swap_events, Kmem, MemoryEvents, MemoryStat, NumaCounter, NumaStat,
+        OomControl, SlabEntry, SwapEvents,
     };
 
     static GOOD_VALUE: &str = "\
@@ -710,6 +1410,43 @@ hierarchical_total=1628573 N0=1628573 N1=123
 hierarchical_file=858151 N0=858151 N1=123
 hierarchical_anon=770402 N0=770402 N1=123
 hierarchical_unevictable=20 N0=20 N1=123
+";
+
+    static GOOD_VALUE_SINGLE_NODE: &str = "\
+total=4566 N0=4566
+file=3941 N0=3941
+anon=625 N0=625
+unevictable=0 N0=0
+hierarchical_total=1238412 N0=1238412
+hierarchical_file=902198 N0=902198
+hierarchical_anon=336214 N0=336214
+hierarchical_unevictable=0 N0=0
+";
+
+    static GOOD_VALUE_V2: &str = "\
+anon N0=1884160 N1=4096
+file N0=5656576 N1=0
+kernel_stack N0=49152 N1=0
+pagetables N0=200704 N1=0
+sec_pagetables N0=0 N1=0
+shmem N0=8192 N1=0
+file_mapped N0=3112960 N1=0
+file_dirty N0=0 N1=0
+file_writeback N0=0 N1=0
+swapcached N0=0 N1=0
+anon_thp N0=0 N1=0
+file_thp N0=0 N1=0
+shmem_thp N0=0 N1=0
+inactive_anon N0=1871872 N1=4096
+active_anon N0=12288 N1=0
+inactive_file N0=2174976 N1=0
+active_file N0=3481600 N1=0
+unevictable N0=0 N1=0
+slab_reclaimable N0=118512 N1=0
+slab_unreclaimable N0=143584 N1=0
+workingset_refault_anon N0=0 N1=0
+workingset_refault_file N0=0 N1=0
+workingset_activate_anon N0=0 N1=0
 ";
 
     static GOOD_OOMCONTROL_VAL: &str = "\
@@ -759,31 +1496,70 @@ total_unevictable 81920
 
     #[test]
     fn test_parse_numa_stat() {
+        let counter = |total, n0, n1| {
+            Some(NumaCounter {
+                total,
+                per_node: vec![(0, n0), (1, n1)].into_iter().collect(),
+            })
+        };
         let ok = parse_numa_stat(GOOD_VALUE.to_string()).unwrap();
         assert_eq!(
             ok,
             NumaStat {
-                total_pages: 51189,
-                total_pages_per_node: vec![51189, 123],
-                file_pages: 50175,
-                file_pages_per_node: vec![50175, 123],
-                anon_pages: 1014,
-                anon_pages_per_node: vec![1014, 123],
-                unevictable_pages: 0,
-                unevictable_pages_per_node: vec![0, 123],
-
-                hierarchical_total_pages: 1628573,
-                hierarchical_total_pages_per_node: vec![1628573, 123],
-                hierarchical_file_pages: 858151,
-                hierarchical_file_pages_per_node: vec![858151, 123],
-                hierarchical_anon_pages: 770402,
-                hierarchical_anon_pages_per_node: vec![770402, 123],
-                hierarchical_unevictable_pages: 20,
-                hierarchical_unevictable_pages_per_node: vec![20, 123],
+                total_pages: counter(51189, 51189, 123),
+                file_pages: counter(50175, 50175, 123),
+                anon_pages: counter(1014, 1014, 123),
+                unevictable_pages: counter(0, 0, 123),
+
+                hierarchical_total_pages: counter(1628573, 1628573, 123),
+                hierarchical_file_pages: counter(858151, 858151, 123),
+                hierarchical_anon_pages: counter(770402, 770402, 123),
+                hierarchical_unevictable_pages: counter(20, 20, 123),
+
+                extra: HashMap::new(),
             }
         );
     }
 
+    #[test]
+    fn test_parse_numa_stat_v1_single_node() {
+        let ok = parse_numa_stat(GOOD_VALUE_SINGLE_NODE.to_string()).unwrap();
+        let total = ok.total_pages.unwrap();
+        assert_eq!(total.total, 4566);
+        assert_eq!(total.per_node.get(&0), Some(&4566));
+        assert_eq!(ok.hierarchical_anon_pages.unwrap().total, 336214);
+        assert!(ok.extra.is_empty());
+    }
+
+    #[test]
+    fn test_parse_numa_stat_v2() {
+        let ok = parse_numa_stat(GOOD_VALUE_V2.to_string()).unwrap();
+        let anon = ok.anon_pages.unwrap();
+        assert_eq!(anon.total, 1884160 + 4096);
+        assert_eq!(anon.per_node.get(&1), Some(&4096));
+        assert_eq!(ok.file_pages.unwrap().total, 5656576);
+        assert_eq!(ok.unevictable_pages.unwrap().total, 0);
+        assert_eq!(ok.total_pages, None);
+        assert_eq!(ok.hierarchical_total_pages, None);
+
+        let shmem = &ok.extra["shmem"];
+        assert_eq!(shmem.total, 8192);
+        assert_eq!(shmem.per_node.get(&0), Some(&8192));
+        assert_eq!(ok.extra["active_anon"].total, 12288);
+        assert_eq!(ok.extra["workingset_refault_anon"].total, 0);
+        assert_eq!(ok.extra.len(), 20);
+    }
+
+    #[test]
+    fn test_parse_numa_stat_bad() {
+        assert_eq!(parse_numa_stat("".to_string()).unwrap(), NumaStat::default());
+        assert!(parse_numa_stat("total=1 X0=1".to_string()).is_err());
+        assert!(parse_numa_stat("total=x N0=1".to_string()).is_err());
+        assert!(parse_numa_stat("anon 4096 N0=4096".to_string()).is_err());
+        assert!(parse_numa_stat("a=b=c N0=1".to_string()).is_err());
+        assert!(parse_numa_stat("total=1 N0".to_string()).is_err());
+    }
+
     #[test]
     fn test_parse_oom_control() {
         let ok = parse_oom_control(GOOD_OOMCONTROL_VAL.to_string()).unwrap();
@@ -803,43 +1579,141 @@ total_unevictable 81920
         assert_eq!(
             ok,
             MemoryStat {
-                cache: 178880512,
-                rss: 4206592,
-                rss_huge: 0,
-                shmem: 106496,
-                mapped_file: 7491584,
-                dirty: 114688,
-                writeback: 49152,
-                swap: 0,
-                pgpgin: 213928,
-                pgpgout: 169220,
-                pgfault: 87064,
-                pgmajfault: 202,
-                inactive_anon: 0,
-                active_anon: 4153344,
-                inactive_file: 84779008,
-                active_file: 94273536,
-                unevictable: 0,
-                hierarchical_memory_limit: 9223372036854771712,
-                hierarchical_memsw_limit: 9223372036854771712,
-                total_cache: 4200333312,
-                total_rss: 2927677440,
-                total_rss_huge: 0,
-                total_shmem: 590061568,
-                total_mapped_file: 1086164992,
-                total_dirty: 1769472,
-                total_writeback: 602112,
-                total_swap: 0,
-                total_pgpgin: 5267326291,
-                total_pgpgout: 5265586647,
-                total_pgfault: 9947902469,
-                total_pgmajfault: 25132,
-                total_inactive_anon: 585981952,
-                total_active_anon: 2928996352,
-                total_inactive_file: 1272135680,
-                total_active_file: 2338816000,
-                total_unevictable: 81920,
+                cache: Some(178880512),
+                rss: Some(4206592),
+                rss_huge: Some(0),
+                shmem: Some(106496),
+                mapped_file: Some(7491584),
+                dirty: Some(114688),
+                writeback: Some(49152),
+                swap: Some(0),
+                pgpgin: Some(213928),
+                pgpgout: Some(169220),
+                pgfault: Some(87064),
+                pgmajfault: Some(202),
+                inactive_anon: Some(0),
+                active_anon: Some(4153344),
+                inactive_file: Some(84779008),
+                active_file: Some(94273536),
+                unevictable: Some(0),
+                hierarchical_memory_limit: Some(9223372036854771712),
+                hierarchical_memsw_limit: Some(9223372036854771712),
+                total_cache: Some(4200333312),
+                total_rss: Some(2927677440),
+                total_rss_huge: Some(0),
+                total_shmem: Some(590061568),
+                total_mapped_file: Some(1086164992),
+                total_dirty: Some(1769472),
+                total_writeback: Some(602112),
+                total_swap: Some(0),
+                total_pgpgin: Some(5267326291),
+                total_pgpgout: Some(5265586647),
+                total_pgfault: Some(9947902469),
+                total_pgmajfault: Some(25132),
+                total_inactive_anon: Some(585981952),
+                total_active_anon: Some(2928996352),
+                total_inactive_file: Some(1272135680),
+                total_active_file: Some(2338816000),
+                total_unevictable: Some(81920),
+                extra: HashMap::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_memory_stat_reordered() {
+        // rss_huge and shmem are missing, the order is shuffled and there is an unknown key
+        let ok = parse_memory_stat("rss 2\ncache 1\nworkingset_refault 3\n".to_string()).unwrap();
+        assert_eq!(ok.cache, Some(1));
+        assert_eq!(ok.rss, Some(2));
+        assert_eq!(ok.rss_huge, None);
+        assert_eq!(ok.shmem, None);
+        assert_eq!(ok.extra.get("workingset_refault"), Some(&3));
+
+        assert!(parse_memory_stat("cache 1 2\n".to_string()).is_err());
+        assert!(parse_memory_stat("cache x\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_memory_events() {
+        let ok = parse_memory_events(
+            "low 1\nhigh 2\nmax 3\noom 4\noom_kill 5\noom_group_kill 6\nsock_throttled 7\n"
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            ok,
+            MemoryEvents {
+                low: 1,
+                high: 2,
+                max: 3,
+                oom: 4,
+                oom_kill: 5,
+                oom_group_kill: 6,
+            }
+        );
+        // older kernels lack oom_group_kill
+        let ok = parse_memory_events("low 0\nhigh 0\nmax 0\noom 1\noom_kill 1\n".to_string());
+        assert_eq!(ok.unwrap().oom_group_kill, 0);
+
+        assert!(parse_memory_events("oom\n".to_string()).is_err());
+        assert!(parse_memory_events("oom x\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_swap_events() {
+        let ok = parse_swap_events("high 1\nmax 2\nfail 3\n".to_string()).unwrap();
+        assert_eq!(
+            ok,
+            SwapEvents {
+                high: 1,
+                max: 2,
+                fail: 3,
+            }
+        );
+    }
+
+    static GOOD_SLABINFO_VAL: &str = "\
+slabinfo - version: 2.1
+# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab> : tunables <limit> <batchcount> <sharedfactor> : slabdata <active_slabs> <num_slabs> <sharedavail>
+dentry               378    378    192   21    1 : tunables    0    0    0 : slabdata     18     18      0
+inode_cache          104    104    600   13    2 : tunables    0    0    0 : slabdata      8      8      0
+kmalloc-64           256    256     64   64    1 : tunables    0    0    0 : slabdata      4      4      0
+";
+
+    #[test]
+    fn test_parse_slabinfo() {
+        let ok = parse_slabinfo(GOOD_SLABINFO_VAL.to_string()).unwrap();
+        assert_eq!(ok.len(), 3);
+        assert_eq!(
+            ok[0],
+            SlabEntry {
+                name: "dentry".to_string(),
+                active_objs: 378,
+                num_objs: 378,
+                objsize: 192,
+                objperslab: 21,
+                pagesperslab: 1,
             }
         );
+        assert!(parse_slabinfo("".to_string()).unwrap().is_empty());
+        assert!(parse_slabinfo("dentry 378 378 x 21 1\n".to_string()).is_err());
+        assert!(parse_slabinfo("dentry 378\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_top_slabs() {
+        let kmem = Kmem {
+            fail_cnt: 0,
+            limit_in_bytes: 0,
+            usage_in_bytes: 0,
+            max_usage_in_bytes: 0,
+            slabinfo: parse_slabinfo(GOOD_SLABINFO_VAL.to_string()).unwrap(),
+        };
+        let top = kmem.top_slabs(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].name, "dentry");
+        assert_eq!(top[1].name, "inode_cache");
+        assert_eq!(kmem.top_slabs(10).len(), 3);
     }
 }
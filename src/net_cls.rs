@@ -44,9 +44,11 @@ impl ControllerInternal for NetClsController {
         let res: &NetworkResources = &res.network;
 
         if res.update_values {
-            let _ = self.set_class(res.class_id);
-            if self.get_class()? != res.class_id {
-                return Err(Error::new(Other));
+            if let Some(class_id) = res.class_id {
+                self.set_class(class_id)?;
+                if self.get_class()? != class_id {
+                    return Err(Error::new(Other));
+                }
             }
         }
         return Ok(());
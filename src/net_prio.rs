@@ -46,7 +46,7 @@ impl ControllerInternal for NetPrioController {
 
         if res.update_values {
             for i in &res.priorities {
-                let _ = self.set_if_prio(&i.name, i.priority);
+                self.set_if_prio(&i.name, i.priority)?;
             }
         }
 
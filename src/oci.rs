@@ -0,0 +1,681 @@
+//! This module contains conversions between `Resources` and the `linux.resources` section of an
+//! OCI runtime bundle's `config.json`, as described by the
+//! [OCI runtime specification](https://github.com/opencontainers/runtime-spec/blob/master/config-linux.md#control-groups).
+//!
+//! It is only available when the `oci` feature is enabled.
+//!
+//! Fields that are absent from the OCI configuration are left as `None` (or with `update_values`
+//! unset) in `Resources`, so applying the converted resources leaves them untouched. Device rules
+//! with types the devices controller does not understand (i.e. FIFOs) are skipped.
+//!
+//! Both conversions fail with `InvalidResources` instead of dropping a value, as that would lift
+//! the restriction: converting from the OCI configuration fails if the `cpus` or `mems` list of the
+//! CPU section cannot be parsed or a hugepage limit is negative, and converting to it fails if a
+//! value does not fit into the type used by the specification, e.g. a class ID above `u32::MAX`.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use log::*;
+
+use oci_spec::runtime::{
+    LinuxBlockIo, LinuxCpu, LinuxDeviceCgroup, LinuxDeviceType, LinuxHugepageLimit,
+    LinuxInterfacePriority, LinuxMemory, LinuxMemoryBuilder, LinuxNetwork, LinuxPids, LinuxRdma,
+    LinuxResources, LinuxThrottleDevice, LinuxThrottleDeviceBuilder, LinuxWeightDeviceBuilder,
+};
+
+use crate::cpu::CpuQuota;
+use crate::cpuset::CpuList;
+use crate::devices::{DevicePermissions, DeviceType};
+use crate::error::ErrorKind::*;
+use crate::error::*;
+use crate::pid::PidMax;
+use crate::{
+    BlkIoDeviceResource, BlkIoDeviceThrottleResource, BlkIoResources, CpuResources,
+    DeviceResource, DeviceResources, HugePageResource, HugePageResources, MemoryResources,
+    NetworkPriority, NetworkResources, PidResources, RdmaResource, RdmaResources, Resources,
+};
+
+impl TryFrom<LinuxResources> for Resources {
+    type Error = Error;
+
+    fn try_from(oci: LinuxResources) -> Result<Resources> {
+        Ok(Resources {
+            memory: oci
+                .memory()
+                .as_ref()
+                .map(memory_from_oci)
+                .unwrap_or_default(),
+            pid: oci.pids().as_ref().map(pid_from_oci).unwrap_or_default(),
+            cpu: oci
+                .cpu()
+                .as_ref()
+                .map(cpu_from_oci)
+                .transpose()?
+                .unwrap_or_default(),
+            devices: oci
+                .devices()
+                .as_ref()
+                .map(|d| devices_from_oci(d))
+                .unwrap_or_default(),
+            network: oci
+                .network()
+                .as_ref()
+                .map(network_from_oci)
+                .unwrap_or_default(),
+            hugepages: oci
+                .hugepage_limits()
+                .as_ref()
+                .map(|h| hugepages_from_oci(h))
+                .transpose()?
+                .unwrap_or_default(),
+            blkio: oci
+                .block_io()
+                .as_ref()
+                .map(blkio_from_oci)
+                .transpose()?
+                .unwrap_or_default(),
+            rdma: oci.rdma().as_ref().map(rdma_from_oci).unwrap_or_default(),
+            unified: oci.unified().clone().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<Resources> for LinuxResources {
+    type Error = Error;
+
+    fn try_from(res: Resources) -> Result<LinuxResources> {
+        let mut oci = LinuxResources::default();
+        if res.memory.update_values {
+            oci.set_memory(Some(memory_to_oci(&res.memory)));
+        }
+        if res.pid.update_values {
+            oci.set_pids(Some(pid_to_oci(&res.pid)));
+        }
+        if res.cpu.update_values {
+            oci.set_cpu(Some(cpu_to_oci(&res.cpu)));
+        }
+        if res.devices.update_values {
+            oci.set_devices(Some(devices_to_oci(&res.devices)));
+        }
+        if res.network.update_values {
+            oci.set_network(Some(network_to_oci(&res.network)?));
+        }
+        if res.hugepages.update_values {
+            oci.set_hugepage_limits(Some(hugepages_to_oci(&res.hugepages)?));
+        }
+        if res.blkio.update_values {
+            oci.set_block_io(Some(blkio_to_oci(&res.blkio)?));
+        }
+        if res.rdma.update_values {
+            oci.set_rdma(Some(rdma_to_oci(&res.rdma)));
+        }
+        if !res.unified.is_empty() {
+            oci.set_unified(Some(res.unified));
+        }
+        Ok(oci)
+    }
+}
+
+/// Converts `value` to another integer type, failing with `InvalidResources` if it does not fit.
+fn in_range<T, U>(what: &str, value: T) -> Result<U>
+where
+    T: Copy + fmt::Display,
+    U: TryFrom<T>,
+{
+    U::try_from(value)
+        .map_err(|_| Error::new(InvalidResources(format!("{} out of range: {}", what, value))))
+}
+
+fn memory_from_oci(mem: &LinuxMemory) -> MemoryResources {
+    MemoryResources {
+        update_values: true,
+        kernel_memory_limit: mem.kernel(),
+        memory_hard_limit: mem.limit(),
+        memory_soft_limit: mem.reservation(),
+        kernel_tcp_memory_limit: mem.kernel_tcp(),
+        memory_swap_limit: mem.swap(),
+        swappiness: mem.swappiness(),
+        oom_kill_disable: mem.disable_oom_killer(),
+        use_hierarchy: mem.use_hierarchy(),
+        ..Default::default()
+    }
+}
+
+fn memory_to_oci(res: &MemoryResources) -> LinuxMemory {
+    let mut mem = LinuxMemoryBuilder::default();
+    if let Some(limit) = res.kernel_memory_limit {
+        mem = mem.kernel(limit);
+    }
+    if let Some(limit) = res.memory_hard_limit {
+        mem = mem.limit(limit);
+    }
+    if let Some(limit) = res.memory_soft_limit {
+        mem = mem.reservation(limit);
+    }
+    if let Some(limit) = res.kernel_tcp_memory_limit {
+        mem = mem.kernel_tcp(limit);
+    }
+    if let Some(limit) = res.memory_swap_limit {
+        mem = mem.swap(limit);
+    }
+    if let Some(swappiness) = res.swappiness {
+        mem = mem.swappiness(swappiness);
+    }
+    if let Some(b) = res.oom_kill_disable {
+        mem = mem.disable_oom_killer(b);
+    }
+    if let Some(b) = res.use_hierarchy {
+        mem = mem.use_hierarchy(b);
+    }
+    // Every field has a default, so building cannot fail.
+    mem.build().unwrap()
+}
+
+fn pid_from_oci(pids: &LinuxPids) -> PidResources {
+    // The runtime-spec treats zero and negative values as "no limit".
+    let max = if pids.limit() > 0 {
+        PidMax::Value(pids.limit())
+    } else {
+        PidMax::Max
+    };
+    PidResources {
+        update_values: true,
+        maximum_number_of_processes: max,
+    }
+}
+
+fn pid_to_oci(res: &PidResources) -> LinuxPids {
+    let mut pids = LinuxPids::default();
+    pids.set_limit(match res.maximum_number_of_processes {
+        PidMax::Max => -1,
+        PidMax::Value(v) => v,
+    });
+    pids
+}
+
+fn cpu_list_from_oci(what: &str, list: &Option<String>) -> Result<Option<CpuList>> {
+    match list {
+        Some(list) => match list.parse() {
+            Ok(list) => Ok(Some(list)),
+            Err(_) => Err(Error::new(InvalidResources(format!(
+                "invalid {} list: {:?}",
+                what, list
+            )))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn cpu_from_oci(cpu: &LinuxCpu) -> Result<CpuResources> {
+    Ok(CpuResources {
+        update_values: true,
+        cpus: cpu_list_from_oci("CPU", cpu.cpus())?,
+        mems: cpu_list_from_oci("memory node", cpu.mems())?,
+        shares: cpu.shares(),
+        quota: cpu.quota().map(CpuQuota::from),
+        period: cpu.period(),
+        realtime_runtime: cpu.realtime_runtime(),
+        realtime_period: cpu.realtime_period(),
+        burst: cpu.burst(),
+        ..Default::default()
+    })
+}
+
+fn cpu_to_oci(res: &CpuResources) -> LinuxCpu {
+    let mut cpu = LinuxCpu::default();
+    cpu.set_cpus(res.cpus.as_ref().map(CpuList::to_string));
+    cpu.set_mems(res.mems.as_ref().map(CpuList::to_string));
+    cpu.set_shares(res.shares);
+    cpu.set_quota(res.quota.map(i64::from));
+    cpu.set_period(res.period);
+    cpu.set_realtime_runtime(res.realtime_runtime);
+    cpu.set_realtime_period(res.realtime_period);
+    cpu.set_burst(res.burst);
+    cpu
+}
+
+fn devices_from_oci(devices: &[LinuxDeviceCgroup]) -> DeviceResources {
+    DeviceResources {
+        update_values: true,
+        devices: devices
+            .iter()
+            .filter_map(|dev| {
+                let devtype = match dev.typ().unwrap_or_default() {
+                    LinuxDeviceType::A => DeviceType::All,
+                    LinuxDeviceType::B => DeviceType::Block,
+                    LinuxDeviceType::C | LinuxDeviceType::U => DeviceType::Char,
+                    LinuxDeviceType::P => {
+                        warn!("skipping unsupported device rule: {}", dev);
+                        return None;
+                    }
+                };
+                Some(DeviceResource {
+                    allow: dev.allow(),
+                    devtype,
+                    major: dev.major().unwrap_or(-1),
+                    minor: dev.minor().unwrap_or(-1),
+                    access: dev
+                        .access()
+                        .as_ref()
+                        .map(|a| a.chars().filter_map(DevicePermissions::from_char).collect())
+                        .unwrap_or_else(DevicePermissions::all),
+                })
+            })
+            .collect(),
+    }
+}
+
+fn devices_to_oci(res: &DeviceResources) -> Vec<LinuxDeviceCgroup> {
+    res.devices
+        .iter()
+        .map(|dev| {
+            let mut oci = LinuxDeviceCgroup::default();
+            oci.set_allow(dev.allow);
+            oci.set_typ(Some(match dev.devtype {
+                DeviceType::All => LinuxDeviceType::A,
+                DeviceType::Block => LinuxDeviceType::B,
+                DeviceType::Char => LinuxDeviceType::C,
+            }));
+            oci.set_major(if dev.major == -1 { None } else { Some(dev.major) });
+            oci.set_minor(if dev.minor == -1 { None } else { Some(dev.minor) });
+            oci.set_access(Some(
+                dev.access.iter().map(DevicePermissions::to_char).collect(),
+            ));
+            oci
+        })
+        .collect()
+}
+
+fn network_from_oci(net: &LinuxNetwork) -> NetworkResources {
+    NetworkResources {
+        update_values: true,
+        class_id: net.class_id().map(u64::from),
+        priorities: net
+            .priorities()
+            .as_ref()
+            .map(|p| {
+                p.iter()
+                    .map(|prio| NetworkPriority {
+                        name: prio.name().clone(),
+                        priority: u64::from(prio.priority()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn network_to_oci(res: &NetworkResources) -> Result<LinuxNetwork> {
+    let mut net = LinuxNetwork::default();
+    net.set_class_id(res.class_id.map(|id| in_range("class ID", id)).transpose()?);
+    if !res.priorities.is_empty() {
+        net.set_priorities(Some(
+            res.priorities
+                .iter()
+                .map(|prio| {
+                    let mut oci = LinuxInterfacePriority::default();
+                    oci.set_name(prio.name.clone());
+                    oci.set_priority(in_range("network priority", prio.priority)?);
+                    Ok(oci)
+                })
+                .collect::<Result<_>>()?,
+        ));
+    }
+    Ok(net)
+}
+
+fn hugepages_from_oci(limits: &[LinuxHugepageLimit]) -> Result<HugePageResources> {
+    Ok(HugePageResources {
+        update_values: true,
+        limits: limits
+            .iter()
+            .map(|l| {
+                Ok(HugePageResource {
+                    size: l.page_size().clone(),
+                    limit: in_range("hugepage limit", l.limit())?,
+                })
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn hugepages_to_oci(res: &HugePageResources) -> Result<Vec<LinuxHugepageLimit>> {
+    res.limits
+        .iter()
+        .map(|l| {
+            let mut oci = LinuxHugepageLimit::default();
+            oci.set_page_size(l.size.clone());
+            oci.set_limit(in_range("hugepage limit", l.limit)?);
+            Ok(oci)
+        })
+        .collect()
+}
+
+fn throttle_from_oci(
+    devs: &Option<Vec<LinuxThrottleDevice>>,
+) -> Result<Vec<BlkIoDeviceThrottleResource>> {
+    devs.as_ref()
+        .map(|d| {
+            d.iter()
+                .map(|dev| {
+                    Ok(BlkIoDeviceThrottleResource {
+                        major: in_range("device major number", dev.major())?,
+                        minor: in_range("device minor number", dev.minor())?,
+                        rate: dev.rate(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| Ok(Vec::new()))
+}
+
+fn throttle_to_oci(
+    devs: &[BlkIoDeviceThrottleResource],
+) -> Result<Option<Vec<LinuxThrottleDevice>>> {
+    if devs.is_empty() {
+        return Ok(None);
+    }
+    devs.iter()
+        .map(|dev| {
+            Ok(LinuxThrottleDeviceBuilder::default()
+                .major(in_range::<_, i64>("device major number", dev.major)?)
+                .minor(in_range::<_, i64>("device minor number", dev.minor)?)
+                .rate(dev.rate)
+                .build()
+                .unwrap())
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn blkio_from_oci(blkio: &LinuxBlockIo) -> Result<BlkIoResources> {
+    Ok(BlkIoResources {
+        update_values: true,
+        weight: blkio.weight(),
+        leaf_weight: blkio.leaf_weight(),
+        weight_device: blkio
+            .weight_device()
+            .as_ref()
+            .map(|d| {
+                d.iter()
+                    .map(|dev| {
+                        Ok(BlkIoDeviceResource {
+                            major: in_range("device major number", dev.major())?,
+                            minor: in_range("device minor number", dev.minor())?,
+                            weight: dev.weight(),
+                            leaf_weight: dev.leaf_weight(),
+                        })
+                    })
+                    .collect::<Result<_>>()
+            })
+            .transpose()?
+            .unwrap_or_default(),
+        throttle_read_bps_device: throttle_from_oci(blkio.throttle_read_bps_device())?,
+        throttle_read_iops_device: throttle_from_oci(blkio.throttle_read_iops_device())?,
+        throttle_write_bps_device: throttle_from_oci(blkio.throttle_write_bps_device())?,
+        throttle_write_iops_device: throttle_from_oci(blkio.throttle_write_iops_device())?,
+    })
+}
+
+fn blkio_to_oci(res: &BlkIoResources) -> Result<LinuxBlockIo> {
+    let mut blkio = LinuxBlockIo::default();
+    blkio.set_weight(res.weight);
+    blkio.set_leaf_weight(res.leaf_weight);
+    if !res.weight_device.is_empty() {
+        blkio.set_weight_device(Some(
+            res.weight_device
+                .iter()
+                .map(|dev| {
+                    let mut oci = LinuxWeightDeviceBuilder::default()
+                        .major(in_range::<_, i64>("device major number", dev.major)?)
+                        .minor(in_range::<_, i64>("device minor number", dev.minor)?);
+                    if let Some(weight) = dev.weight {
+                        oci = oci.weight(weight);
+                    }
+                    if let Some(leaf_weight) = dev.leaf_weight {
+                        oci = oci.leaf_weight(leaf_weight);
+                    }
+                    Ok(oci.build().unwrap())
+                })
+                .collect::<Result<_>>()?,
+        ));
+    }
+    blkio.set_throttle_read_bps_device(throttle_to_oci(&res.throttle_read_bps_device)?);
+    blkio.set_throttle_read_iops_device(throttle_to_oci(&res.throttle_read_iops_device)?);
+    blkio.set_throttle_write_bps_device(throttle_to_oci(&res.throttle_write_bps_device)?);
+    blkio.set_throttle_write_iops_device(throttle_to_oci(&res.throttle_write_iops_device)?);
+    Ok(blkio)
+}
+
+fn rdma_from_oci(rdma: &HashMap<String, LinuxRdma>) -> RdmaResources {
+    let mut limits = rdma
+        .iter()
+        .map(|(device, limit)| RdmaResource {
+            device: device.clone(),
+            hca_handles: limit.hca_handles(),
+            hca_objects: limit.hca_objects(),
+        })
+        .collect::<Vec<_>>();
+    // Keep the order stable, the map in the configuration has none.
+    limits.sort_by(|a, b| a.device.cmp(&b.device));
+    RdmaResources {
+        update_values: true,
+        limits,
+    }
+}
+
+fn rdma_to_oci(res: &RdmaResources) -> HashMap<String, LinuxRdma> {
+    res.limits
+        .iter()
+        .map(|l| {
+            let mut oci = LinuxRdma::default();
+            *oci.hca_handles_mut() = l.hca_handles;
+            *oci.hca_objects_mut() = l.hca_objects;
+            (l.device.clone(), oci)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use oci_spec::runtime::LinuxResources;
+
+    use crate::cpu::CpuQuota;
+    use crate::devices::{DevicePermissions, DeviceType};
+    use crate::error::ErrorKind;
+    use crate::pid::PidMax;
+    use crate::{
+        BlkIoDeviceResource, BlkIoDeviceThrottleResource, DeviceResource, HugePageResource,
+        NetworkPriority, Resources,
+    };
+
+    static GOOD_RESOURCES: &str = r#"{
+        "devices": [
+            { "allow": false, "access": "rwm" },
+            { "allow": true, "type": "c", "major": 1, "minor": 3, "access": "rw" },
+            { "allow": true, "type": "p", "major": 1, "minor": 5, "access": "r" }
+        ],
+        "memory": {
+            "limit": 536870912, "reservation": 268435456, "swap": -1, "swappiness": 0,
+            "disableOOMKiller": true
+        },
+        "cpu": { "shares": 1024, "quota": 50000, "period": 100000, "cpus": "0-3", "mems": "0" },
+        "pids": { "limit": 32 },
+        "blockIO": {
+            "weight": 10,
+            "weightDevice": [ { "major": 8, "minor": 0, "weight": 500 } ],
+            "throttleReadBpsDevice": [ { "major": 8, "minor": 0, "rate": 600 } ]
+        },
+        "hugepageLimits": [ { "pageSize": "2MB", "limit": 9223372036854772000 } ],
+        "network": { "classID": 1048577, "priorities": [ { "name": "eth0", "priority": 500 } ] },
+        "rdma": {
+            "mlx5_1": { "hcaHandles": 3, "hcaObjects": 10000 },
+            "mlx4_0": { "hcaObjects": 1000 }
+        },
+        "unified": { "memory.high": "1073741824" }
+    }"#;
+
+    #[test]
+    fn test_from_oci() {
+        let oci: LinuxResources = serde_json::from_str(GOOD_RESOURCES).unwrap();
+        let res = Resources::try_from(oci).unwrap();
+
+        assert!(res.memory.update_values);
+        assert_eq!(res.memory.memory_hard_limit, Some(512 * 1024 * 1024));
+        assert_eq!(res.memory.memory_soft_limit, Some(256 * 1024 * 1024));
+        assert_eq!(res.memory.memory_swap_limit, Some(-1));
+        assert_eq!(res.memory.kernel_memory_limit, None);
+        assert_eq!(res.memory.swappiness, Some(0));
+        assert_eq!(res.memory.oom_kill_disable, Some(true));
+        assert_eq!(res.memory.use_hierarchy, None);
+
+        assert_eq!(res.cpu.shares, Some(1024));
+        assert_eq!(res.cpu.quota, Some(CpuQuota::Micros(50000)));
+        assert_eq!(res.cpu.cpus, Some("0-3".parse().unwrap()));
+        assert_eq!(res.cpu.realtime_runtime, None);
+
+        assert_eq!(res.pid.maximum_number_of_processes, PidMax::Value(32));
+
+        assert_eq!(
+            res.devices.devices,
+            vec![
+                DeviceResource {
+                    allow: false,
+                    devtype: DeviceType::All,
+                    major: -1,
+                    minor: -1,
+                    access: DevicePermissions::all(),
+                },
+                DeviceResource {
+                    allow: true,
+                    devtype: DeviceType::Char,
+                    major: 1,
+                    minor: 3,
+                    access: vec![DevicePermissions::Read, DevicePermissions::Write],
+                },
+            ]
+        );
+
+        assert_eq!(res.blkio.weight, Some(10));
+        assert_eq!(res.blkio.leaf_weight, None);
+        assert_eq!(
+            res.blkio.weight_device,
+            vec![BlkIoDeviceResource {
+                major: 8,
+                minor: 0,
+                weight: Some(500),
+                leaf_weight: None,
+            }]
+        );
+        assert_eq!(
+            res.blkio.throttle_read_bps_device,
+            vec![BlkIoDeviceThrottleResource {
+                major: 8,
+                minor: 0,
+                rate: 600,
+            }]
+        );
+        assert!(res.blkio.throttle_write_bps_device.is_empty());
+
+        assert_eq!(res.hugepages.limits[0].size, "2MB");
+
+        assert_eq!(res.network.class_id, Some(0x100001));
+        assert_eq!(res.network.priorities[0].name, "eth0");
+        assert_eq!(res.network.priorities[0].priority, 500);
+
+        assert_eq!(res.rdma.limits.len(), 2);
+        assert_eq!(res.rdma.limits[0].device, "mlx4_0");
+        assert_eq!(res.rdma.limits[0].hca_handles, None);
+        assert_eq!(res.rdma.limits[0].hca_objects, Some(1000));
+        assert_eq!(res.rdma.limits[1].hca_handles, Some(3));
+
+        assert_eq!(res.unified.get("memory.high").unwrap(), "1073741824");
+    }
+
+    #[test]
+    fn test_oci_roundtrip() {
+        let oci: LinuxResources = serde_json::from_str(GOOD_RESOURCES).unwrap();
+        let res = Resources::try_from(oci).unwrap();
+        let back = Resources::try_from(LinuxResources::try_from(res.clone()).unwrap()).unwrap();
+        assert_eq!(back, res);
+    }
+
+    fn assert_invalid<T: std::fmt::Debug>(res: crate::error::Result<T>) {
+        match res.unwrap_err().kind() {
+            ErrorKind::InvalidResources(_) => {}
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let oci: LinuxResources = serde_json::from_str(
+            r#"{ "hugepageLimits": [ { "pageSize": "2MB", "limit": -1 },
+                                     { "pageSize": "1GB", "limit": 2 } ] }"#,
+        )
+        .unwrap();
+        assert_invalid(Resources::try_from(oci));
+
+        for blkio in &[
+            r#"{ "weightDevice": [ { "major": -8, "minor": 0, "weight": 500 } ] }"#,
+            r#"{ "throttleReadBpsDevice": [ { "major": 8, "minor": -1, "rate": 600 } ] }"#,
+        ] {
+            let oci: LinuxResources =
+                serde_json::from_str(&format!(r#"{{ "blockIO": {} }}"#, blkio)).unwrap();
+            assert_invalid(Resources::try_from(oci));
+        }
+
+        let mut res = Resources::default();
+        res.network.update_values = true;
+        res.network.class_id = Some(0x1_0000_0001);
+        assert_invalid(LinuxResources::try_from(res.clone()));
+
+        res.network.class_id = Some(0x10_0001);
+        res.network.priorities = vec![NetworkPriority {
+            name: "eth0".to_string(),
+            priority: u64::MAX,
+        }];
+        assert_invalid(LinuxResources::try_from(res.clone()));
+
+        res.network.priorities.clear();
+        res.hugepages.update_values = true;
+        res.hugepages.limits = vec![HugePageResource {
+            size: "2MB".to_string(),
+            limit: u64::MAX,
+        }];
+        assert_invalid(LinuxResources::try_from(res.clone()));
+
+        res.hugepages.limits[0].limit = 2;
+        res.blkio.update_values = true;
+        res.blkio.throttle_read_bps_device = vec![BlkIoDeviceThrottleResource {
+            major: u64::MAX,
+            minor: 0,
+            rate: 600,
+        }];
+        assert_invalid(LinuxResources::try_from(res.clone()));
+
+        res.blkio.throttle_read_bps_device.clear();
+        let oci = LinuxResources::try_from(res).unwrap();
+        assert_eq!(oci.network().as_ref().unwrap().class_id(), Some(0x10_0001));
+        assert_eq!(oci.hugepage_limits().as_ref().unwrap()[0].limit(), 2);
+    }
+
+    #[test]
+    fn test_invalid_cpu_list() {
+        for cpu in &[r#"{ "cpus": "0-a" }"#, r#"{ "mems": "0-4294967295" }"#] {
+            let oci: LinuxResources =
+                serde_json::from_str(&format!(r#"{{ "cpu": {} }}"#, cpu)).unwrap();
+            assert_invalid(Resources::try_from(oci));
+        }
+    }
+
+    #[test]
+    fn test_to_oci_skips_unset() {
+        let oci = LinuxResources::try_from(Resources::default()).unwrap();
+        assert_eq!(oci, LinuxResources::default());
+    }
+}
@@ -18,10 +18,12 @@ use crate::{
 pub struct PidController {
     base: PathBuf,
     path: PathBuf,
+    v2: bool,
 }
 
 /// The values found in the `pids.max` file in a Cgroup's `pids` subsystem.
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PidMax {
     /// This value is returned when the text found `pids.max` is `"max"`.
     Max,
@@ -49,6 +51,9 @@ impl ControllerInternal for PidController {
     fn get_base(&self) -> &PathBuf {
         &self.base
     }
+    fn is_v2(&self) -> bool {
+        self.v2
+    }
 
     fn apply(&self, res: &Resources) -> Result<()> {
         // get the resources that apply to this controller
@@ -56,7 +61,7 @@ impl ControllerInternal for PidController {
 
         if pidres.update_values {
             // apply pid_max
-            let _ = self.set_pid_max(pidres.maximum_number_of_processes);
+            self.set_pid_max(pidres.maximum_number_of_processes)?;
 
             // now, verify
             if self.get_pid_max()? == pidres.maximum_number_of_processes {
@@ -113,6 +118,17 @@ impl PidController {
         Self {
             base: root.clone(),
             path: root,
+            v2: false,
+        }
+    }
+
+    /// Constructs a new `PidController` for the unified hierarchy, with `root` being the root directory
+    /// of the hierarchy.
+    pub fn new_v2(root: PathBuf) -> Self {
+        Self {
+            base: root.clone(),
+            path: root,
+            v2: true,
         }
     }
 
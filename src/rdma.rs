@@ -9,7 +9,9 @@ use std::path::PathBuf;
 use crate::error::*;
 use crate::error::ErrorKind::*;
 
-use crate::{ControllIdentifier, ControllerInternal, Controllers, Resources, Subsystem};
+use crate::{
+    ControllIdentifier, ControllerInternal, Controllers, RdmaResources, Resources, Subsystem,
+};
 
 /// A controller that allows controlling the `rdma` subsystem of a Cgroup.
 ///
@@ -35,7 +37,23 @@ impl ControllerInternal for RdmaController {
         &self.base
     }
 
-    fn apply(&self, _res: &Resources) -> Result<()> {
+    fn apply(&self, res: &Resources) -> Result<()> {
+        // get the resources that apply to this controller
+        let res: &RdmaResources = &res.rdma;
+
+        if res.update_values {
+            for i in &res.limits {
+                let mut max = i.device.clone();
+                if let Some(handles) = i.hca_handles {
+                    max.push_str(&format!(" hca_handle={}", handles));
+                }
+                if let Some(objects) = i.hca_objects {
+                    max.push_str(&format!(" hca_object={}", objects));
+                }
+                self.set_max(&max)?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,309 @@
+//! This module discovers the CPU and NUMA topology of the host, in order to decide which CPUs and
+//! memory nodes to hand to the `cpuset` controller.
+//!
+//! The topology is read from `/sys/devices/system/cpu` and `/sys/devices/system/node`. See the
+//! Kernel's documentation for more information about these files, found at:
+//!  [Documentation/admin-guide/cputopology.rst](https://www.kernel.org/doc/Documentation/admin-guide/cputopology.rst)
+//!
+//! ## Example:
+//!
+//! ```no_run
+//! # use cgroups::topology::Topology;
+//! # use cgroups::cpuset::CpuSetController;
+//! # fn place(cpuset: &CpuSetController) -> cgroups::error::Result<()> {
+//! let topology = Topology::new()?;
+//! // two whole cores on one NUMA node, out of all online CPUs
+//! if let Some(placement) = topology.place_cores(2, &topology.online_cpus()) {
+//!     placement.apply(cpuset)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::cpuset::{CpuList, CpuSetController};
+use crate::error::*;
+use crate::error::ErrorKind::*;
+
+/// A single online CPU, i.e. a hardware thread, and where it is located.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuInfo {
+    /// The ID of the CPU, as used in `cpuset.cpus`.
+    pub id: u64,
+    /// The ID of the socket (physical package) the CPU belongs to.
+    pub socket: u64,
+    /// The ID of the core the CPU belongs to. This is not necessarily unique within a socket, e.g.
+    /// on systems with multiple dies per package, so use `siblings` to tell the cores apart.
+    pub core: u64,
+    /// The NUMA node the CPU belongs to. This is `0` on systems without NUMA.
+    pub node: u64,
+    /// The CPUs that share the core with this CPU, including itself (SMT siblings).
+    pub siblings: CpuList,
+}
+
+/// The topology of the host's online CPUs and NUMA nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Topology {
+    cpus: Vec<CpuInfo>,
+    nodes: BTreeMap<u64, CpuList>,
+}
+
+/// A set of CPUs and memory nodes that tasks can be placed on, see `Topology::place_cores()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Placement {
+    /// The CPUs the tasks should run on.
+    pub cpus: CpuList,
+    /// The memory nodes the tasks should use.
+    pub mems: CpuList,
+}
+
+impl Placement {
+    /// Restricts the control group of `cpuset` to the CPUs and memory nodes of the placement.
+    pub fn apply(&self, cpuset: &CpuSetController) -> Result<()> {
+        cpuset.set_cpus(&self.cpus)?;
+        cpuset.set_mems(&self.mems)
+    }
+}
+
+fn read_from<T: FromStr>(path: &Path) -> Result<T> {
+    let mut string = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut string))
+        .map_err(|e| Error::with_cause(ReadFailed, e))?;
+    string.trim().parse().map_err(|_| Error::new(ParseError))
+}
+
+impl Topology {
+    /// Reads the topology of the host from `/sys/devices/system`.
+    pub fn new() -> Result<Topology> {
+        Topology::from_sysfs("/sys/devices/system")
+    }
+
+    /// Reads the topology from `root`, a directory with the same layout as `/sys/devices/system`.
+    pub fn from_sysfs<P: AsRef<Path>>(root: P) -> Result<Topology> {
+        let root = root.as_ref();
+
+        // without NUMA, there is no node directory and all CPUs are on node 0
+        let node_root = root.join("node");
+        let mut nodes = BTreeMap::new();
+        if node_root.join("online").exists() {
+            let online: CpuList = read_from(&node_root.join("online"))?;
+            for node in online.iter() {
+                let cpus: CpuList =
+                    read_from(&node_root.join(format!("node{}", node)).join("cpulist"))?;
+                nodes.insert(node, cpus);
+            }
+        }
+
+        let cpu_root = root.join("cpu");
+        let online: CpuList = read_from(&cpu_root.join("online"))?;
+        let mut cpus = Vec::new();
+        for id in online.iter() {
+            let topology: PathBuf = cpu_root.join(format!("cpu{}", id)).join("topology");
+            cpus.push(CpuInfo {
+                id,
+                socket: read_from(&topology.join("physical_package_id"))?,
+                core: read_from(&topology.join("core_id"))?,
+                node: nodes
+                    .iter()
+                    .find(|(_, cpus)| cpus.contains(id))
+                    .map(|(&node, _)| node)
+                    .unwrap_or(0),
+                siblings: read_from(&topology.join("thread_siblings_list"))?,
+            });
+        }
+        if nodes.is_empty() {
+            nodes.insert(0, online);
+        }
+
+        Ok(Topology { cpus, nodes })
+    }
+
+    /// The online CPUs, ordered by their IDs.
+    pub fn cpus(&self) -> &[CpuInfo] {
+        &self.cpus
+    }
+
+    /// The IDs of the online CPUs.
+    pub fn online_cpus(&self) -> CpuList {
+        self.cpus.iter().map(|cpu| cpu.id).collect()
+    }
+
+    /// The online NUMA nodes, by ID, along with the CPUs that belong to them.
+    pub fn nodes(&self) -> &BTreeMap<u64, CpuList> {
+        &self.nodes
+    }
+
+    /// The online CPUs grouped by socket.
+    pub fn sockets(&self) -> BTreeMap<u64, CpuList> {
+        let mut fin: BTreeMap<u64, CpuList> = BTreeMap::new();
+        for cpu in &self.cpus {
+            fin.entry(cpu.socket).or_default().insert(cpu.id);
+        }
+        fin
+    }
+
+    /// The online CPUs grouped by core, i.e. each entry is a set of SMT siblings. The cores are
+    /// ordered by their lowest CPU ID.
+    pub fn cores(&self) -> Vec<CpuList> {
+        let online = self.online_cpus();
+        // key each core by its lowest online sibling, which every sibling agrees on
+        let mut fin: BTreeMap<u64, CpuList> = BTreeMap::new();
+        for cpu in &self.cpus {
+            let first = cpu.siblings.intersection(&online).iter().next().unwrap_or(cpu.id);
+            fin.entry(first).or_default().insert(cpu.id);
+        }
+        fin.into_values().collect()
+    }
+
+    /// Picks `cores` whole cores on a single NUMA node out of the CPUs in `available`.
+    ///
+    /// A core is only picked if all of its SMT siblings are available, and only one CPU of each
+    /// core is part of the placement. Thus, the placed tasks do not share a core with each other
+    /// or with anyone else, as long as the other siblings are not handed out either. The NUMA
+    /// nodes are tried in order, and the placement uses the memory of the chosen node.
+    ///
+    /// Returns `None` if `cores` is zero or no node has enough available cores.
+    pub fn place_cores(&self, cores: usize, available: &CpuList) -> Option<Placement> {
+        if cores == 0 {
+            return None;
+        }
+        let all_cores = self.cores();
+        for (&node, node_cpus) in &self.nodes {
+            let cpus = all_cores
+                .iter()
+                .filter(|core| core.is_subset(available) && core.is_subset(node_cpus))
+                .filter_map(|core| core.iter().next())
+                .take(cores)
+                .collect::<CpuList>();
+            if cpus.len() == cores {
+                return Some(Placement {
+                    cpus,
+                    mems: vec![node].into_iter().collect(),
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::cpuset::CpuList;
+    use crate::topology::Topology;
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Builds a fake `/sys/devices/system` with 2 nodes of 2 cores of 2 threads each, where CPU
+    /// `n` and `n + 4` are siblings.
+    fn fake_sysfs(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("cgroups-rs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(&root, "cpu/online", "0-7\n");
+        for cpu in 0..8 {
+            let core = cpu % 4;
+            let topology = format!("cpu/cpu{}/topology", cpu);
+            write(&root, &format!("{}/physical_package_id", topology), &format!("{}\n", core / 2));
+            write(&root, &format!("{}/core_id", topology), &format!("{}\n", core % 2));
+            write(
+                &root,
+                &format!("{}/thread_siblings_list", topology),
+                &format!("{},{}\n", core, core + 4),
+            );
+        }
+        write(&root, "node/online", "0-1\n");
+        write(&root, "node/node0/cpulist", "0-1,4-5\n");
+        write(&root, "node/node1/cpulist", "2-3,6-7\n");
+        root
+    }
+
+    fn list(s: &str) -> CpuList {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_topology() {
+        let root = fake_sysfs("topology");
+        let topology = Topology::from_sysfs(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(topology.online_cpus(), list("0-7"));
+        assert_eq!(topology.nodes().len(), 2);
+        assert_eq!(topology.nodes()[&1], list("2-3,6-7"));
+        let cpu6 = &topology.cpus()[6];
+        assert_eq!((cpu6.socket, cpu6.core, cpu6.node), (1, 0, 1));
+        assert_eq!(cpu6.siblings, list("2,6"));
+
+        let sockets = topology.sockets();
+        assert_eq!(sockets[&0], list("0-1,4-5"));
+        assert_eq!(
+            topology.cores(),
+            vec![list("0,4"), list("1,5"), list("2,6"), list("3,7")]
+        );
+    }
+
+    #[test]
+    fn test_place_cores() {
+        let root = fake_sysfs("place_cores");
+        let topology = Topology::from_sysfs(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let placement = topology.place_cores(2, &list("0-7")).unwrap();
+        assert_eq!(placement.cpus, list("0-1"));
+        assert_eq!(placement.mems, list("0"));
+
+        // CPU 5 is taken, so its sibling 1 must not be used and node 0 lacks a second core
+        let placement = topology.place_cores(2, &list("0-4,6-7")).unwrap();
+        assert_eq!(placement.cpus, list("2-3"));
+        assert_eq!(placement.mems, list("1"));
+
+        assert_eq!(topology.place_cores(3, &list("0-7")), None);
+        assert_eq!(topology.place_cores(0, &list("0-7")), None);
+    }
+
+    #[test]
+    fn test_duplicate_core_ids() {
+        // with several dies per package, core IDs repeat within a socket
+        let root = fake_sysfs("duplicate_core_ids");
+        for cpu in 0..8 {
+            let topology = format!("cpu/cpu{}/topology", cpu);
+            write(&root, &format!("{}/physical_package_id", topology), "0\n");
+            write(&root, &format!("{}/core_id", topology), "0\n");
+        }
+        let topology = Topology::from_sysfs(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            topology.cores(),
+            vec![list("0,4"), list("1,5"), list("2,6"), list("3,7")]
+        );
+        assert_eq!(topology.place_cores(2, &list("0-7")).unwrap().cpus, list("0-1"));
+    }
+
+    #[test]
+    fn test_no_numa() {
+        let root = fake_sysfs("no_numa");
+        fs::remove_dir_all(root.join("node")).unwrap();
+        let topology = Topology::from_sysfs(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(topology.nodes().len(), 1);
+        assert_eq!(topology.nodes()[&0], list("0-7"));
+        assert!(topology.cpus().iter().all(|cpu| cpu.node == 0));
+        assert_eq!(topology.place_cores(4, &list("0-7")).unwrap().cpus, list("0-3"));
+    }
+}
@@ -1,7 +1,9 @@
 //! Some simple tests covering the builder pattern for control groups.
 use cgroups::*;
 use cgroups::cpu::*;
+use cgroups::cpuset::*;
 use cgroups::devices::*;
+use cgroups::freezer::*;
 use cgroups::pid::*;
 use cgroups::memory::*;
 use cgroups::net_cls::*;
@@ -16,7 +18,8 @@ pub fn test_cpu_res_build() {
         .cpu()
             .shares(85)
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let cpu: &CpuController = cg.controller_of().unwrap();
@@ -36,7 +39,8 @@ pub fn test_memory_res_build() {
             .swappiness(70)
             .memory_hard_limit(1024 * 1024 * 1024)
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &MemController = cg.controller_of().unwrap();
@@ -55,7 +59,8 @@ pub fn test_pid_res_build() {
         .pid()
             .maximum_number_of_processes(PidMax::Value(123))
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &PidController = cg.controller_of().unwrap();
@@ -75,7 +80,8 @@ pub fn test_devices_res_build() {
             .device(1, 6, DeviceType::Char, true,
                     vec![DevicePermissions::Read])
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &DevicesController = cg.controller_of().unwrap();
@@ -100,7 +106,8 @@ pub fn test_network_res_build() {
         .network()
             .class_id(1337)
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &NetClsController = cg.controller_of().unwrap();
@@ -117,12 +124,13 @@ pub fn test_hugepages_res_build() {
         .hugepages()
             .limit("2MB".to_string(), 4 * 2 * 1024 * 1024)
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &HugeTlbController = cg.controller_of().unwrap();
-        assert!(c.limit_in_bytes(&"2MB".to_string()).is_ok());
-        assert_eq!(c.limit_in_bytes(&"2MB".to_string()).unwrap(), 4 * 2 * 1024 * 1024);
+        assert!(c.limit_in_bytes("2MB").is_ok());
+        assert_eq!(c.limit_in_bytes("2MB").unwrap(), 4 * 2 * 1024 * 1024);
     }
     cg.delete();
 }
@@ -134,7 +142,8 @@ pub fn test_blkio_res_build() {
         .blkio()
             .weight(100)
             .done()
-        .build();
+        .build()
+        .unwrap();
 
     {
         let c: &BlkIoController = cg.controller_of().unwrap();
@@ -142,3 +151,71 @@ pub fn test_blkio_res_build() {
     }
     cg.delete();
 }
+
+#[test]
+pub fn test_cpuset_res_build() {
+    let v1 = crate::hierarchies::V1::new();
+    let cg: Cgroup = CgroupBuilder::new("test_cpuset_res_build", &v1)
+        .cpu()
+            .mem_hardwall(true)
+            .memory_migrate(true)
+            .done()
+        .build()
+        .unwrap();
+
+    {
+        let c: &CpuSetController = cg.controller_of().unwrap();
+        assert!(c.cpuset().mem_hardwall);
+        assert!(c.cpuset().memory_migrate);
+    }
+    cg.delete();
+}
+
+#[test]
+pub fn test_freezer_res_build() {
+    let v1 = crate::hierarchies::V1::new();
+    let cg: Cgroup = CgroupBuilder::new("test_freezer_res_build", &v1)
+        .freezer()
+            .state(FreezerState::Frozen)
+            .done()
+        .build()
+        .unwrap();
+
+    {
+        let c: &FreezerController = cg.controller_of().unwrap();
+        assert_eq!(c.state().unwrap(), FreezerState::Frozen);
+        c.thaw().unwrap();
+    }
+    cg.delete();
+}
+
+#[test]
+pub fn test_invalid_res_build() {
+    let v1 = crate::hierarchies::V1::new();
+    let cg = CgroupBuilder::new("test_invalid_res_build", &v1)
+        .blkio()
+            .weight(1)
+            .done()
+        .build();
+    assert!(cg.is_err());
+    let cg = Cgroup::load(&v1, "test_invalid_res_build");
+    let c: &BlkIoController = cg.controller_of().unwrap();
+    assert!(!c.exists());
+}
+
+#[test]
+pub fn test_failed_res_build() {
+    let v1 = crate::hierarchies::V1::new();
+    // there is no block device with these numbers, so the kernel rejects the limit
+    let cg = CgroupBuilder::new("test_failed_res_build", &v1)
+        .delete_on_failure(true)
+        .blkio()
+            .throttle_bps()
+                .read(1000, 1000, 10)
+            .done()
+        .build();
+    assert!(cg.is_err());
+    let cg = Cgroup::load(&v1, "test_failed_res_build");
+    let c: &BlkIoController = cg.controller_of().unwrap();
+    assert!(!c.exists());
+}
@@ -1,5 +1,7 @@
 //! Simple unit tests about the control groups system.
-use cgroups::{Cgroup, CgroupPid};
+use cgroups::hierarchies::V2;
+use cgroups::pid::PidController;
+use cgroups::{Cgroup, CgroupPid, Hierarchy};
 
 #[test]
 fn test_tasks_iterator() {
@@ -23,3 +25,36 @@ fn test_tasks_iterator() {
     }
     cg.delete();
 }
+
+#[test]
+fn test_v2_tasks_iterator() {
+    // only runs where the unified hierarchy is mounted and offers the pids controller
+    let hier = match V2::try_new() {
+        Some(hier) => hier,
+        None => return,
+    };
+    let supported = hier.root_control_group().controller_of::<PidController>().is_some();
+    if !supported {
+        return;
+    }
+
+    let pid = libc::pid_t::from(nix::unistd::getpid()) as u64;
+    let cg = Cgroup::new(&hier, String::from("test_v2_tasks_iterator"));
+    {
+        let subtree_control = hier.root().join("cgroup.subtree_control");
+        assert!(std::fs::read_to_string(subtree_control).unwrap().contains("pids"));
+        // all controllers share the same directory, with cgroup.procs instead of tasks
+        let dir = hier.root().join("test_v2_tasks_iterator");
+        assert!(dir.join("cgroup.procs").exists());
+        assert!(!dir.join("tasks").exists());
+
+        cg.add_task(CgroupPid::from(pid)).unwrap();
+        assert_eq!(cg.tasks(), vec![CgroupPid::from(pid)]);
+
+        // moving the task back to the root removes it
+        let root = hier.root_control_group();
+        root.add_task(CgroupPid::from(pid)).unwrap();
+        assert_eq!(cg.tasks(), vec![]);
+    }
+    cg.delete();
+}
@@ -0,0 +1,279 @@
+//! Integration tests about the cpu controller
+use cgroups::cpu::{shares_to_weight, CpuController, CpuQuota};
+use cgroups::error::ErrorKind;
+use cgroups::cgroup_builder::CgroupBuilder;
+use cgroups::hierarchies::V2;
+use cgroups::{Cgroup, CpuResources, Hierarchy, Resources};
+
+fn rt_resources(runtime: i64, period: u64) -> Resources {
+    Resources {
+        cpu: CpuResources {
+            update_values: true,
+            realtime_runtime: Some(runtime),
+            realtime_period: Some(period),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_rt_budget() {
+    let hier = cgroups::hierarchies::V1::new();
+    let parent = Cgroup::new(&hier, String::from("test_rt_budget"));
+    let child = Cgroup::new(&hier, String::from("test_rt_budget/child"));
+    {
+        parent.apply(&rt_resources(100_000, 1_000_000)).unwrap();
+        let cpu: &CpuController = parent.controller_of().unwrap();
+        assert_eq!(cpu.rt_runtime().unwrap(), 100_000);
+        assert_eq!(cpu.rt_period().unwrap(), 1_000_000);
+
+        // 5% of the time is within the parent's 10%
+        child.apply(&rt_resources(5_000, 100_000)).unwrap();
+        let cpu: &CpuController = child.controller_of().unwrap();
+        assert_eq!(cpu.rt_runtime().unwrap(), 5_000);
+        assert_eq!(cpu.rt_period().unwrap(), 100_000);
+
+        // 20% is not
+        match child.apply(&rt_resources(20_000, 100_000)) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("the parent's realtime budget should have been exceeded"),
+        }
+        assert_eq!(cpu.rt_runtime().unwrap(), 5_000);
+
+        // shrinking the period below the old runtime only works with the runtime written first
+        child.apply(&rt_resources(50_000, 1_000_000)).unwrap();
+        child.apply(&rt_resources(2_000, 40_000)).unwrap();
+        assert_eq!(cpu.rt_runtime().unwrap(), 2_000);
+        assert_eq!(cpu.rt_period().unwrap(), 40_000);
+
+        // 6% fits on its own, but not next to a sibling with another 6%
+        child.apply(&rt_resources(6_000, 100_000)).unwrap();
+        let sibling = Cgroup::new(&hier, String::from("test_rt_budget/sibling"));
+        let res = sibling.apply(&rt_resources(6_000, 100_000));
+        let sibling_cpu: &CpuController = sibling.controller_of().unwrap();
+        assert_eq!(sibling_cpu.rt_runtime().unwrap(), 0);
+        sibling.delete();
+        match res {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("the siblings should have exceeded the parent's realtime budget"),
+        }
+
+        // the parent cannot shrink below the budget of its child
+        match parent.apply(&rt_resources(50_000, 1_000_000)) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("the child's realtime budget should not have fit"),
+        }
+
+        child.apply(&rt_resources(0, 1_000_000)).unwrap();
+    }
+    child.delete();
+    parent.delete();
+}
+
+#[test]
+fn test_cfs_quota() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_cfs_quota"));
+    {
+        let cpu: &CpuController = cg.controller_of().unwrap();
+        assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Unlimited);
+
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                quota: Some(CpuQuota::Micros(50_000)),
+                period: Some(100_000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        cg.apply(&res).unwrap();
+        assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Micros(50_000));
+
+        res.cpu.quota = Some(CpuQuota::Unlimited);
+        cg.apply(&res).unwrap();
+        assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Unlimited);
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_cfs_budget() {
+    let hier = cgroups::hierarchies::V1::new();
+    let parent = Cgroup::new(&hier, String::from("test_cfs_budget"));
+    let child = Cgroup::new(&hier, String::from("test_cfs_budget/child"));
+    let grandchild = Cgroup::new(&hier, String::from("test_cfs_budget/child/grandchild"));
+    {
+        let cpu: &CpuController = parent.controller_of().unwrap();
+        cpu.set_cfs_quota(CpuQuota::Micros(50_000)).unwrap();
+        cpu.set_cfs_period(100_000).unwrap();
+
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                quota: Some(CpuQuota::Micros(80_000)),
+                period: Some(100_000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // 0.8 CPUs do not fit into the 0.5 of the parent, even through an unlimited child
+        for cg in [&child, &grandchild].iter() {
+            match cg.apply(&res) {
+                Err(e) => match e.kind() {
+                    ErrorKind::InvalidResources(_) => {}
+                    k => panic!("unexpected error kind {:?}", k),
+                },
+                Ok(()) => panic!("the parent's CFS bandwidth should have been exceeded"),
+            }
+        }
+
+        res.cpu.quota = Some(CpuQuota::Micros(20_000));
+        res.cpu.period = Some(50_000);
+        grandchild.apply(&res).unwrap();
+        let cpu: &CpuController = grandchild.controller_of().unwrap();
+        assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Micros(20_000));
+    }
+    grandchild.delete();
+    child.delete();
+    parent.delete();
+}
+
+#[test]
+fn test_cpu_limit() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = CgroupBuilder::new("test_cpu_limit", &hier)
+        .cpu()
+        .cpus_limit(1.5)
+        .done()
+        .build()
+        .unwrap();
+    {
+        let cpu: &CpuController = cg.controller_of().unwrap();
+        assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Micros(150_000));
+        assert_eq!(cpu.cfs_period().unwrap(), 100_000);
+        assert_eq!(cpu.cpu_limit().unwrap(), Some(1.5));
+
+        cpu.set_cpu_limit(0.25).unwrap();
+        assert_eq!(cpu.cpu_limit().unwrap(), Some(0.25));
+        assert!(cpu.set_cpu_limit(0.001).is_err());
+        assert!(cpu.set_cpu_limit(f64::NAN).is_err());
+        assert_eq!(cpu.cpu_limit().unwrap(), Some(0.25));
+
+        cpu.set_cfs_quota(CpuQuota::Unlimited).unwrap();
+        assert_eq!(cpu.cpu_limit().unwrap(), None);
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_burst_and_uclamp() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_burst_and_uclamp"));
+    {
+        let cpu: &CpuController = cg.controller_of().unwrap();
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                quota: Some(CpuQuota::Micros(50_000)),
+                period: Some(100_000),
+                burst: Some(20_000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match cg.apply(&res) {
+            Ok(()) => {
+                assert_eq!(cpu.cfs_burst().unwrap(), 20_000);
+
+                // lowering the quota below the old burst needs the burst to go first
+                res.cpu.quota = Some(CpuQuota::Micros(10_000));
+                res.cpu.burst = Some(5_000);
+                cg.apply(&res).unwrap();
+                assert_eq!(cpu.cfs_quota().unwrap(), CpuQuota::Micros(10_000));
+                assert_eq!(cpu.cfs_burst().unwrap(), 5_000);
+
+                // raising the burst above the old quota needs the quota to go first
+                res.cpu.quota = Some(CpuQuota::Micros(80_000));
+                res.cpu.burst = Some(40_000);
+                cg.apply(&res).unwrap();
+                assert_eq!(cpu.cfs_burst().unwrap(), 40_000);
+
+                // a burst above the current quota is rejected before anything is written
+                res.cpu.quota = None;
+                res.cpu.burst = Some(90_000);
+                match cg.apply(&res) {
+                    Err(e) => match e.kind() {
+                        ErrorKind::InvalidResources(_) => {}
+                        k => panic!("unexpected error kind {:?}", k),
+                    },
+                    Ok(()) => panic!("the burst should have exceeded the quota"),
+                }
+                assert_eq!(cpu.cfs_burst().unwrap(), 40_000);
+            }
+            Err(e) => assert_eq!(e.kind(), &ErrorKind::Unsupported),
+        }
+
+        match cpu.set_uclamp_min(12.5) {
+            Ok(()) => assert_eq!(cpu.uclamp_min().unwrap(), 12.5),
+            Err(e) => assert_eq!(e.kind(), &ErrorKind::Unsupported),
+        }
+        // cpu.weight only exists in the unified hierarchy
+        assert_eq!(cpu.set_weight(100).unwrap_err().kind(), &ErrorKind::Unsupported);
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_v2_cpu() {
+    // only runs where the unified hierarchy is mounted and offers the cpu controller
+    let hier = match V2::try_new() {
+        Some(hier) => hier,
+        None => return,
+    };
+    let supported = hier.root_control_group().controller_of::<CpuController>().is_some();
+    if !supported {
+        return;
+    }
+
+    let cg = Cgroup::new(&hier, String::from("test_v2_cpu"));
+    {
+        let mut res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                shares: Some(2048),
+                quota: Some(CpuQuota::Micros(50_000)),
+                period: Some(100_000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        cg.apply(&res).unwrap();
+        let cpu: &CpuController = cg.controller_of().unwrap();
+        assert_eq!(cpu.weight().unwrap(), shares_to_weight(2048));
+        assert_eq!(cpu.max().unwrap(), (CpuQuota::Micros(50_000), 100_000));
+
+        // cpu.max holds both values, so the one that is not given is kept
+        res.cpu.shares = None;
+        res.cpu.quota = None;
+        res.cpu.period = Some(200_000);
+        cg.apply(&res).unwrap();
+        assert_eq!(cpu.max().unwrap(), (CpuQuota::Micros(50_000), 200_000));
+
+        res.cpu.quota = Some(CpuQuota::Unlimited);
+        cg.apply(&res).unwrap();
+        assert_eq!(cpu.max().unwrap(), (CpuQuota::Unlimited, 200_000));
+        assert_eq!(cpu.cpu_limit().unwrap(), None);
+    }
+    cg.delete();
+}
@@ -1,6 +1,8 @@
-use cgroups::cpuset::CpuSetController;
+use cgroups::cpuset::{CpuList, CpuSetController};
 use cgroups::error::ErrorKind;
-use cgroups::Cgroup;
+use cgroups::hierarchies::V2;
+use cgroups::topology::Topology;
+use cgroups::{Cgroup, CpuResources, Hierarchy, Resources};
 
 #[test]
 fn test_cpuset_memory_pressure_root_cg() {
@@ -15,3 +17,83 @@ fn test_cpuset_memory_pressure_root_cg() {
     }
     cg.delete();
 }
+
+#[test]
+fn test_cpuset_cpu_list() {
+    let hier = cgroups::hierarchies::V1::new();
+    let root = hier.root_control_group();
+    let host: &CpuSetController = root.controller_of().unwrap();
+    let host_cpus = host.cpuset().effective_cpus;
+    let host_mems = host.cpuset().effective_mems;
+    assert!(!host_cpus.is_empty());
+
+    let cg = Cgroup::new(&hier, String::from("test_cpuset_cpu_list"));
+    {
+        let cpuset: &CpuSetController = cg.controller_of().unwrap();
+        let first: CpuList = host_cpus.iter().take(1).collect();
+        cpuset.set_cpus(&first).unwrap();
+        cpuset.set_mems(&host_mems).unwrap();
+        assert_eq!(cpuset.cpuset().cpus, first);
+        assert_eq!(cpuset.cpuset().mems, host_mems);
+        assert!(cpuset.cpuset().effective_cpus.is_subset(&host_cpus));
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_cpuset_placement() {
+    let topology = Topology::new().unwrap();
+    let placement = topology.place_cores(1, &topology.online_cpus()).unwrap();
+    assert_eq!(placement.cpus.len(), 1);
+
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_cpuset_placement"));
+    {
+        let cpuset: &CpuSetController = cg.controller_of().unwrap();
+        placement.apply(cpuset).unwrap();
+        assert_eq!(cpuset.cpuset().cpus, placement.cpus);
+        assert_eq!(cpuset.cpuset().mems, placement.mems);
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_v2_cpuset() {
+    // only runs where the unified hierarchy is mounted and offers the cpuset controller
+    let hier = match V2::try_new() {
+        Some(hier) => hier,
+        None => return,
+    };
+    let root = hier.root_control_group();
+    let host: &CpuSetController = match root.controller_of() {
+        Some(host) => host,
+        None => return,
+    };
+    // the effective lists are named differently in the unified hierarchy
+    let host_cpus = host.cpuset().effective_cpus;
+    let host_mems = host.cpuset().effective_mems;
+    assert!(!host_cpus.is_empty());
+    assert!(!host_mems.is_empty());
+
+    let cg = Cgroup::new(&hier, String::from("test_v2_cpuset"));
+    {
+        let first: CpuList = host_cpus.iter().take(1).collect();
+        let res = Resources {
+            cpu: CpuResources {
+                update_values: true,
+                cpus: Some(first.clone()),
+                mems: Some(host_mems.clone()),
+                // V1-only settings are skipped
+                memory_migrate: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        res.validate(&hier).unwrap();
+        cg.apply(&res).unwrap();
+        let cpuset: &CpuSetController = cg.controller_of().unwrap();
+        assert_eq!(cpuset.cpuset().cpus, first);
+        assert_eq!(cpuset.cpuset().effective_cpus, first);
+    }
+    cg.delete();
+}
@@ -0,0 +1,222 @@
+//! Integration tests about the memory controller
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::process::{Child, Command, Stdio};
+
+use cgroups::error::ErrorKind;
+use cgroups::hierarchies::V2;
+use cgroups::memory::{MemController, MoveCharge, PressureLevel, PressureMode};
+use cgroups::{Cgroup, CgroupPid, Controller, Hierarchy, MemoryResources, Resources};
+
+/// Wait at most `timeout_ms` milliseconds for `fd` to become readable.
+fn poll_readable(fd: i32, timeout_ms: i32) -> bool {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut pfd, 1, timeout_ms) == 1 }
+}
+
+/// Spawn a process in the control group of `mem` that allocates `bytes` bytes of memory.
+fn spawn_hog(mem: &MemController, bytes: u64) -> Child {
+    // `tail` keeps the whole input in memory as it never sees a newline
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(format!("read x; head -c {} /dev/zero | tail", bytes))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    mem.add_task(&CgroupPid::from(&child)).unwrap();
+    child.stdin.take().unwrap().write_all(b"\n").unwrap();
+    child
+}
+
+#[test]
+fn test_oom_notifier() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_oom_notifier"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        mem.set_limit(8 * 1024 * 1024).unwrap();
+        let _ = mem.set_memswap_limit(8 * 1024 * 1024);
+        let mut notifier = mem.oom_notifier().unwrap();
+        assert!(!poll_readable(notifier.as_raw_fd(), 0));
+
+        let mut child = spawn_hog(mem, 256 * 1024 * 1024);
+
+        assert!(poll_readable(notifier.as_raw_fd(), 10_000));
+        assert!(notifier.wait().unwrap() >= 1);
+        child.wait().unwrap();
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_threshold_notifier() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_threshold_notifier"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        let mut notifier = mem.register_threshold(4 * 1024 * 1024).unwrap();
+        assert!(!poll_readable(notifier.as_raw_fd(), 0));
+
+        let mut child = spawn_hog(mem, 16 * 1024 * 1024);
+        assert!(poll_readable(notifier.as_raw_fd(), 10_000));
+        assert!(notifier.wait().unwrap() >= 1);
+        child.wait().unwrap();
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_pressure_listener() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_pressure_listener"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        mem.set_limit(8 * 1024 * 1024).unwrap();
+        let mut notifier = mem
+            .pressure_listener(PressureLevel::Low, PressureMode::Local)
+            .unwrap();
+        assert!(!poll_readable(notifier.as_raw_fd(), 0));
+
+        // going over the limit forces the kernel to reclaim
+        let mut child = spawn_hog(mem, 32 * 1024 * 1024);
+        assert!(poll_readable(notifier.as_raw_fd(), 10_000));
+        assert!(notifier.wait().unwrap() >= 1);
+        child.wait().unwrap();
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_oom_kill_disable() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_oom_kill_disable"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        mem.set_oom_kill_disable(true).unwrap();
+        assert!(mem.memory_stat().oom_control.oom_kill_disable);
+        mem.set_oom_kill_disable(false).unwrap();
+        assert!(!mem.memory_stat().oom_control.oom_kill_disable);
+        // memory.oom.group only exists in the unified hierarchy
+        assert!(mem.set_oom_group(true).is_err());
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_force_empty() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_force_empty"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        let tmp = std::env::temp_dir()
+            .join(format!("cgroups-rs-force-empty-{}", std::process::id()));
+        // fill the page cache from inside the control group
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(format!("read x; dd if=/dev/zero of={} bs=1M count=16", tmp.display()))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        mem.add_task(&CgroupPid::from(&child)).unwrap();
+        child.stdin.take().unwrap().write_all(b"\n").unwrap();
+        child.wait().unwrap();
+
+        let dropped = mem.force_empty();
+        let _ = std::fs::remove_file(&tmp);
+        assert!(dropped.unwrap() > 0);
+        // memory.reclaim only exists in the unified hierarchy
+        assert!(mem.reclaim(4096).is_err());
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_move_charge_at_immigrate() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_move_charge_at_immigrate"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        // recent kernels have dropped charge moving and only accept the empty set
+        if mem.set_move_charge_at_immigrate(MoveCharge::ANON | MoveCharge::FILE).is_ok() {
+            assert_eq!(
+                mem.memory_stat().move_charge_at_immigrate,
+                MoveCharge::ANON | MoveCharge::FILE
+            );
+        }
+        mem.set_move_charge_at_immigrate(MoveCharge::empty()).unwrap();
+        assert!(mem.memory_stat().move_charge_at_immigrate.is_empty());
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_reset_peaks() {
+    let hier = cgroups::hierarchies::V1::new();
+    let cg = Cgroup::new(&hier, String::from("test_reset_peaks"));
+    {
+        let mem: &MemController = cg.controller_of().unwrap();
+        let mut child = spawn_hog(mem, 16 * 1024 * 1024);
+        child.wait().unwrap();
+        let peak = mem.memory_stat().max_usage_in_bytes;
+        assert!(peak >= 16 * 1024 * 1024);
+
+        cg.reset_peaks().unwrap();
+        assert!(mem.memory_stat().max_usage_in_bytes < peak);
+    }
+    cg.delete();
+}
+
+#[test]
+fn test_v2_memory_limits() {
+    // only runs where the unified hierarchy is mounted and offers the memory controller
+    let hier = match V2::try_new() {
+        Some(hier) => hier,
+        None => return,
+    };
+    let supported = hier.root_control_group().controller_of::<MemController>().is_some();
+    if !supported {
+        return;
+    }
+
+    let cg = Cgroup::new(&hier, String::from("test_v2_memory_limits"));
+    {
+        let mut res = Resources {
+            memory: MemoryResources {
+                update_values: true,
+                memory_hard_limit: Some(64 * 1024 * 1024),
+                memory_swap_limit: Some(96 * 1024 * 1024),
+                memory_high: Some(48 * 1024 * 1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        cg.apply(&res).unwrap();
+        let mem: &MemController = cg.controller_of().unwrap();
+        assert_eq!(mem.max().unwrap(), 64 * 1024 * 1024);
+        assert_eq!(mem.high().unwrap(), 48 * 1024 * 1024);
+        // memory.swap.max does not exist without swap accounting
+        if let Ok(swap) = mem.swap_max() {
+            assert_eq!(swap, 32 * 1024 * 1024);
+        }
+        // the V1 max usage files do not exist, so this must not fail
+        cg.reset_peaks().unwrap();
+
+        res.memory.memory_hard_limit = None;
+        match cg.apply(&res) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidResources(_) => {}
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(()) => panic!("a memory+swap limit without a memory limit should be rejected"),
+        }
+    }
+    cg.delete();
+}
@@ -1,6 +1,11 @@
 //! Integration test about setting resources using `apply()`
+use cgroups::cpu::CpuQuota;
+use cgroups::cpuset::CpuList;
+use cgroups::error::ErrorKind;
+use cgroups::hugetlb::HugeTlbController;
 use cgroups::pid::{PidController, PidMax};
-use cgroups::{Cgroup, PidResources, Resources};
+use cgroups::{Cgroup, CpuResources, HugePageResource, HugePageResources, Hierarchy};
+use cgroups::{PidResources, Resources};
 
 #[test]
 fn pid_resources() {
@@ -24,3 +29,75 @@ fn pid_resources() {
     }
     cg.delete();
 }
+
+fn assert_invalid(res: &Resources) {
+    let v1 = cgroups::hierarchies::V1::new();
+    match res.validate(&v1) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidResources(_) => {}
+            k => panic!("unexpected error kind {:?}", k),
+        },
+        Ok(()) => panic!("resources should have been rejected: {:?}", res),
+    }
+}
+
+#[test]
+fn test_validate_default() {
+    let v1 = cgroups::hierarchies::V1::new();
+    assert!(Resources::default().validate(&v1).is_ok());
+}
+
+#[test]
+fn test_validate_cpu() {
+    let res = Resources {
+        cpu: CpuResources {
+            update_values: true,
+            cpus: Some(vec![CpuList::MAX_ID].into_iter().collect()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_invalid(&res);
+
+    let res = Resources {
+        cpu: CpuResources {
+            update_values: true,
+            quota: Some(CpuQuota::Micros(10)),
+            period: Some(100_000),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_invalid(&res);
+
+    let res = Resources {
+        cpu: CpuResources {
+            update_values: true,
+            quota: Some(CpuQuota::Micros(10_000)),
+            burst: Some(20_000),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_invalid(&res);
+}
+
+#[test]
+fn test_validate_hugepage_size() {
+    let res = Resources {
+        hugepages: HugePageResources {
+            update_values: true,
+            limits: vec![HugePageResource {
+                size: "3KB".to_string(),
+                limit: 0,
+            }],
+        },
+        ..Default::default()
+    };
+    let v1 = cgroups::hierarchies::V1::new();
+    let root = v1.root_control_group();
+    // without the hugetlb controller, hugepage limits are never applied and thus not checked
+    if root.controller_of::<HugeTlbController>().is_some() {
+        assert_invalid(&res);
+    }
+}
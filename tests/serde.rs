@@ -0,0 +1,57 @@
+//! Round-trip tests for the optional `serde` support.
+#![cfg(feature = "serde")]
+
+use cgroups::devices::{DevicePermissions, DeviceType};
+use cgroups::pid::PidMax;
+use cgroups::{CpuResources, DeviceResource, DeviceResources, PidResources, Resources};
+
+#[test]
+fn test_resources_roundtrip() {
+    let res = Resources {
+        pid: PidResources {
+            update_values: true,
+            maximum_number_of_processes: PidMax::Value(512),
+        },
+        cpu: CpuResources {
+            update_values: true,
+            cpus: Some("0-3".parse().unwrap()),
+            shares: Some(512),
+            ..Default::default()
+        },
+        devices: DeviceResources {
+            update_values: true,
+            devices: vec![DeviceResource {
+                allow: true,
+                devtype: DeviceType::Char,
+                major: 1,
+                minor: 3,
+                access: vec![DevicePermissions::Read, DevicePermissions::Write],
+            }],
+        },
+        ..Default::default()
+    };
+
+    let json = serde_json::to_string(&res).unwrap();
+    let back: Resources = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, res);
+}
+
+#[test]
+fn test_resources_partial() {
+    // fields that are missing, e.g. from configurations written by older versions, are defaulted
+    let json = r#"{
+        "memory": { "update_values": true, "memory_hard_limit": 1073741824 },
+        "cpu": { "update_values": true, "cpus": "0-1" }
+    }"#;
+    let res: Resources = serde_json::from_str(json).unwrap();
+    assert!(res.memory.update_values);
+    assert_eq!(res.memory.memory_hard_limit, Some(1024 * 1024 * 1024));
+    assert_eq!(res.memory.swappiness, None);
+    assert_eq!(res.cpu.cpus, Some("0-1".parse().unwrap()));
+    assert_eq!(res.cpu.shares, None);
+    assert_eq!(res.pid, PidResources::default());
+    assert!(res.unified.is_empty());
+
+    let res: Resources = serde_json::from_str("{}").unwrap();
+    assert_eq!(res, Resources::default());
+}